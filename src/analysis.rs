@@ -1,2 +1,5 @@
+//! Analyses over parsed commits. [`metrics`] extracts raw per-file and
+//! per-author data, [`derived`] combines metrics into scores.
+
 pub mod derived;
 pub mod metrics;
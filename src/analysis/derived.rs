@@ -1,13 +1,66 @@
-use crate::repo::CommitInfo;
-use std::collections::HashMap;
+//! Analyses derived from [`metrics`](super::metrics): decay, concentration,
+//! coupling statistics and hotspots.
+
+use crate::repo::{CommitInfo, LineOwners};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::analysis::metrics::*;
 
+/// One value for each file of a [`FilePair`], in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PairValues<T> {
+    pub first: T,
+    pub second: T,
+}
+
+/// Coupling measures for one file pair, see [`get_coupling_stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct CouplingStats {
+    pub shared_revisions: usize,
+    pub revisions: PairValues<usize>,
+    pub average_revisions: f64,
+    /// Shared revisions as a percentage of the average revisions of the pair.
+    pub degree: f64,
+    /// Fraction of all changesets that touch both files.
+    pub support: f64,
+    /// Fraction of changes to the first file that also touch the second, and
+    /// the other way around.
+    pub confidence: PairValues<f64>,
+}
+
+/// Decay of one file and the terms it combines, see [`get_decay`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Decay {
+    /// Between 0.0 (actively maintained) and 1.0 (abandoned).
+    pub score: f64,
+    /// Time since the file last changed, relative to the decay threshold.
+    pub staleness: f64,
+    /// Time since the primary owner was last active, relative to the decay
+    /// threshold.
+    pub inactivity: f64,
+    /// The primary owner's share of the file.
+    pub concentration: f64,
+}
+
 const DAYS_TO_SECONDS: i64 = 24 * 60 * 60;
 
-pub fn get_decay(commits: &[CommitInfo], decay_threshold: i64) -> HashMap<String, f64> {
-    let mut file_decays: HashMap<String, f64> = HashMap::new();
+/// Decay per file, combining staleness, the
+/// primary owner's inactivity and ownership concentration. Files and owners
+/// idle for `decay_threshold` days count as fully stale. Co-authored commits
+/// are credited according to `credit`. With `line_owners` (see
+/// [`read_blame`](crate::repo::read_blame)), the primary owner is whoever
+/// wrote most of the file's current lines, and concentration is their share
+/// of those lines instead of the file's commits.
+pub fn get_decay(
+    commits: &[CommitInfo],
+    decay_threshold: i64,
+    credit: Credit,
+    line_owners: Option<&HashMap<String, LineOwners>>,
+) -> HashMap<String, Decay> {
+    let mut file_decays: HashMap<String, Decay> = HashMap::new();
     let decay_threshold = decay_threshold * DAYS_TO_SECONDS;
 
     let time = SystemTime::now()
@@ -16,45 +69,501 @@ pub fn get_decay(commits: &[CommitInfo], decay_threshold: i64) -> HashMap<String
         .as_secs() as i64;
 
     let files_last_modified = get_files_last_modified(commits);
-    let file_owners = get_owners(commits);
-    let file_primary_owners = get_primary_owners(&file_owners);
-    let file_concentrations = get_file_concentrations(&file_owners, &file_primary_owners);
-    let users_last_active = get_user_last_active(commits);
+    let file_owners = get_owners(commits, credit);
+    let mut file_primary_owners = get_primary_owners(&file_owners);
+    let mut file_concentrations = get_file_concentrations(&file_owners, &file_primary_owners);
+    if let Some(line_owners) = line_owners {
+        let line_owners: HashMap<String, Owners> = line_owners
+            .iter()
+            .map(|(path, owners)| (path.clone(), owners.into()))
+            .collect();
+        let line_primary_owners = get_primary_owners(&line_owners);
+        file_concentrations.extend(get_file_concentrations(&line_owners, &line_primary_owners));
+        file_primary_owners.extend(line_primary_owners);
+    }
+    let users_last_active = get_user_last_active(commits, credit);
 
     for path in file_owners.keys() {
         let last_modified = files_last_modified.get(path).unwrap();
         let primary_owner = file_primary_owners.get(path).unwrap();
         let concentration = file_concentrations.get(path).unwrap();
-        let user_last_active = users_last_active.get(primary_owner).unwrap();
 
         let staleness = ((time - last_modified) as f64 / decay_threshold as f64).min(1.0);
-        let inactivity = ((time - user_last_active) as f64 / decay_threshold as f64).min(1.0);
-        let decay = (0.7 * staleness + 0.3 * inactivity * concentration).min(1.0);
+        // Blame can credit lines to authors with no commits in the analyzed
+        // range, who count as fully inactive.
+        let inactivity = users_last_active
+            .get(primary_owner)
+            .map_or(1.0, |last_active| {
+                ((time - last_active) as f64 / decay_threshold as f64).min(1.0)
+            });
+        let score = (0.7 * staleness + 0.3 * inactivity * concentration).min(1.0);
 
-        file_decays.insert(path.clone(), decay);
+        file_decays.insert(
+            path.clone(),
+            Decay {
+                score,
+                staleness,
+                inactivity,
+                concentration: *concentration,
+            },
+        );
     }
 
     file_decays
 }
 
+/// Fractal value of each file: one minus the sum of squared ownership shares.
+/// 0.0 means a single author, values towards 1.0 mean work fragmented across
+/// many authors.
+pub fn get_fractal_values(file_owners: &HashMap<String, Owners>) -> HashMap<String, f64> {
+    file_owners
+        .iter()
+        .map(|(path, owners)| {
+            let total = owners.total();
+            let concentration = owners
+                .authors
+                .values()
+                .map(|p| (p / total).powi(2))
+                .sum::<f64>();
+            (path.clone(), 1.0 - concentration)
+        })
+        .collect()
+}
+
+/// Share of each file's contributions, commits or blamed lines depending on
+/// `file_owners`, made by its primary owner.
 pub fn get_file_concentrations(
-    file_owners: &HashMap<String, HashMap<String, usize>>,
+    file_owners: &HashMap<String, Owners>,
     file_primary_owners: &HashMap<String, String>,
 ) -> HashMap<String, f64> {
     let mut files: HashMap<String, f64> = HashMap::new();
 
-    for (path, authors) in file_owners {
-        let concentration = *authors
-            .get(
-                file_primary_owners
-                    .get(path)
-                    .expect("primary owner invalid"),
-            )
-            .unwrap() as f64
-            / authors.values().sum::<usize>() as f64;
+    for (path, owners) in file_owners {
+        let concentration = owners.share(
+            file_primary_owners
+                .get(path)
+                .expect("primary owner invalid"),
+        );
 
         files.insert(path.clone(), concentration);
     }
 
     files
 }
+
+/// Result of [`get_truck_factor`].
+#[derive(Debug, Clone, Default)]
+pub struct TruckFactor {
+    /// Number of files considered.
+    pub files: usize,
+    /// Number of authors whose departure orphans more than half the files.
+    pub factor: usize,
+    /// The departing authors, most knowledgeable first.
+    pub authors: Vec<CriticalAuthor>,
+}
+
+/// One of the authors a [`TruckFactor`] counts.
+#[derive(Debug, Clone, Serialize)]
+pub struct CriticalAuthor {
+    pub author: String,
+    /// Files their departure leaves without a knowledgeable author.
+    pub files: Vec<String>,
+}
+
+/// Authors knowledgeable about each file under the degree-of-authorship
+/// model (Fritz et al.), as used by Avelino et al. to estimate truck factors:
+/// `DOA = 3.293 + 1.098 * FA + 0.164 * DL - 0.321 * ln(1 + AC)`, where FA is
+/// whether the author created the file, DL their changes and AC everyone
+/// else's. An author knows a file when their DOA is at least 3.293 and at
+/// least 75% of the file's highest DOA.
+pub fn get_file_authors(
+    commits: &[CommitInfo],
+    credit: Credit,
+) -> HashMap<String, BTreeSet<String>> {
+    let file_creators = get_file_creators(commits);
+
+    get_owners(commits, credit)
+        .into_iter()
+        .map(|(path, owners)| {
+            let total = owners.total();
+            let creator = file_creators.get(&path);
+            let doas: Vec<(String, f64)> = owners
+                .authors
+                .into_iter()
+                .map(|(author, changes)| {
+                    let first_authorship = if creator == Some(&author) { 1.0 } else { 0.0 };
+                    let doa = 3.293 + 1.098 * first_authorship + 0.164 * changes
+                        - 0.321 * (1.0 + total - changes).ln();
+                    (author, doa)
+                })
+                .collect();
+
+            let max_doa = doas.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+            let authors = doas
+                .into_iter()
+                .filter(|(_, doa)| *doa >= 3.293 && *doa / max_doa >= 0.75)
+                .map(|(author, _)| author)
+                .collect();
+            (path, authors)
+        })
+        .collect()
+}
+
+/// Greedily removes the author who knows the most files until more than half
+/// of the files have no knowledgeable author left.
+pub fn get_truck_factor(file_authors: &HashMap<String, BTreeSet<String>>) -> TruckFactor {
+    let mut remaining: HashMap<&String, BTreeSet<&String>> = file_authors
+        .iter()
+        .map(|(path, authors)| (path, authors.iter().collect()))
+        .collect();
+    let mut truck_factor = TruckFactor {
+        files: file_authors.len(),
+        ..TruckFactor::default()
+    };
+
+    loop {
+        let orphaned = remaining.values().filter(|p| p.is_empty()).count();
+        if orphaned * 2 > remaining.len() {
+            return truck_factor;
+        }
+
+        let mut files_known: HashMap<&String, usize> = HashMap::new();
+        for author in remaining.values().flatten() {
+            *files_known.entry(author).or_default() += 1;
+        }
+        let Some((author, _)) = files_known
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        else {
+            return truck_factor;
+        };
+
+        let mut sole_files: Vec<String> = remaining
+            .iter()
+            .filter(|(_, authors)| authors.len() == 1 && authors.contains(author))
+            .map(|(path, _)| (*path).clone())
+            .collect();
+        sole_files.sort();
+
+        for authors in remaining.values_mut() {
+            authors.remove(author);
+        }
+        truck_factor.factor += 1;
+        truck_factor.authors.push(CriticalAuthor {
+            author: author.clone(),
+            files: sole_files,
+        });
+    }
+}
+
+/// Truck factor of the repository, scoped `.`, followed by that of every
+/// directory up to `depth` levels deep in path order, each computed from the
+/// files below it.
+pub fn get_scoped_truck_factors(
+    file_authors: &HashMap<String, BTreeSet<String>>,
+    depth: usize,
+) -> Vec<(String, TruckFactor)> {
+    let directories = file_authors.keys().flat_map(|path| {
+        let directories: Vec<&str> = path.split('/').collect();
+        (1..directories.len().min(depth + 1)).map(move |level| directories[..level].join("/"))
+    });
+    let scopes = std::iter::once(".".to_string()).chain(directories.unique().sorted());
+
+    scopes
+        .map(|scope| {
+            let prefix = format!("{scope}/");
+            let scoped_files: HashMap<String, BTreeSet<String>> = file_authors
+                .iter()
+                .filter(|(path, _)| scope == "." || path.starts_with(&prefix))
+                .map(|(path, authors)| (path.clone(), authors.clone()))
+                .collect();
+            let truck_factor = get_truck_factor(&scoped_files);
+            (scope, truck_factor)
+        })
+        .collect()
+}
+
+/// How much of one file's or directory's knowledge departed authors take
+/// with them, see [`get_knowledge_loss`].
+#[derive(Debug, Clone)]
+pub struct KnowledgeLoss {
+    pub owner: String,
+    pub owner_departed: bool,
+    /// Departed authors who contributed to the path.
+    pub departed: BTreeSet<String>,
+    /// Fraction of the path's contributions made by departed authors.
+    pub lost: f64,
+}
+
+/// Authors whose newest activity is more than `inactive_days` days ago.
+pub fn get_inactive_authors(
+    commits: &[CommitInfo],
+    inactive_days: i64,
+    credit: Credit,
+) -> BTreeSet<String> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    get_user_last_active(commits, credit)
+        .into_iter()
+        .filter(|(_, last_active)| time - last_active > inactive_days * DAYS_TO_SECONDS)
+        .map(|(author, _)| author)
+        .collect()
+}
+
+/// Adds up each author's contributions to the files below every directory,
+/// keyed by directory (e.g. `src` and `src/analysis`) then author.
+pub fn get_directory_owners(file_owners: &HashMap<String, Owners>) -> HashMap<String, Owners> {
+    let mut directories: HashMap<String, Owners> = HashMap::new();
+
+    for (path, owners) in file_owners {
+        let mut directory = path.as_str();
+        while let Some((parent, _)) = directory.rsplit_once('/') {
+            let directory_owners = &mut directories.entry(parent.to_string()).or_default().authors;
+            for (author, contribution) in &owners.authors {
+                *directory_owners.entry(author.clone()).or_default() += contribution;
+            }
+            directory = parent;
+        }
+    }
+
+    directories
+}
+
+/// For every path a departed author contributed to, its primary owner,
+/// whether they departed, and the fraction of contributions departed
+/// authors made. Works on commit-based owners as well as on blamed lines.
+pub fn get_knowledge_loss(
+    owners: &HashMap<String, Owners>,
+    departed: &BTreeSet<String>,
+) -> HashMap<String, KnowledgeLoss> {
+    let primary_owners = get_primary_owners(owners);
+
+    owners
+        .iter()
+        .filter_map(|(path, owners)| {
+            let departed_authors: BTreeSet<String> = owners
+                .authors
+                .keys()
+                .filter(|author| departed.contains(*author))
+                .cloned()
+                .collect();
+            if departed_authors.is_empty() {
+                return None;
+            }
+
+            let lost = departed_authors
+                .iter()
+                .map(|p| owners.share(p))
+                .sum::<f64>();
+            let owner = primary_owners[path].clone();
+            Some((
+                path.clone(),
+                KnowledgeLoss {
+                    owner_departed: departed.contains(&owner),
+                    owner,
+                    departed: departed_authors,
+                    lost,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Scores each file by its revision count times its weight, lines of code plus
+/// total indentation complexity, so flat files are still ranked by size. Both
+/// are normalized against the largest value so scores fall between 0.0 and
+/// 1.0.
+pub fn get_hotspots(
+    revisions: &HashMap<String, usize>,
+    complexities: &HashMap<String, Complexity>,
+) -> HashMap<String, f64> {
+    let max_revisions = complexities
+        .keys()
+        .filter_map(|path| revisions.get(path))
+        .max()
+        .copied()
+        .unwrap_or(0)
+        .max(1);
+    let weight = |complexity: &Complexity| complexity.lines + complexity.total;
+    let max_weight = complexities.values().map(weight).max().unwrap_or(0).max(1);
+
+    complexities
+        .iter()
+        .filter_map(|(path, complexity)| {
+            let revisions = *revisions.get(path)? as f64 / max_revisions as f64;
+            let weight = weight(complexity) as f64 / max_weight as f64;
+            Some((path.clone(), revisions * weight))
+        })
+        .collect()
+}
+
+/// Coupling statistics for every co-changing pair, computed over the same
+/// changesets as `get_coupling` so revision counts exclude oversized commits.
+pub fn get_coupling_stats(
+    commits: &[CommitInfo],
+    max_changeset_size: usize,
+) -> HashMap<FilePair, CouplingStats> {
+    let changesets = get_changesets(commits, max_changeset_size);
+    let mut revisions: HashMap<&String, usize> = HashMap::new();
+
+    for file in changesets.iter().flatten() {
+        *revisions.entry(file).or_default() += 1;
+    }
+
+    get_coupling(commits, max_changeset_size)
+        .into_iter()
+        .map(|(pair, shared_revisions)| {
+            let revisions = PairValues {
+                first: revisions[&pair.first],
+                second: revisions[&pair.second],
+            };
+            let average_revisions = (revisions.first + revisions.second) as f64 / 2.0;
+            let shared = shared_revisions as f64;
+
+            let stats = CouplingStats {
+                shared_revisions,
+                revisions,
+                average_revisions,
+                degree: shared / average_revisions * 100.0,
+                support: shared / changesets.len() as f64,
+                confidence: PairValues {
+                    first: shared / revisions.first as f64,
+                    second: shared / revisions.second as f64,
+                },
+            };
+
+            (pair, stats)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{FileChange, FileStatus};
+
+    fn commit(author: &str, status: FileStatus) -> CommitInfo {
+        CommitInfo {
+            hash: String::new(),
+            author: author.to_string(),
+            author_name: author.to_string(),
+            author_email: String::new(),
+            timestamp: 0,
+            committer: author.to_string(),
+            committer_name: author.to_string(),
+            committer_email: String::new(),
+            committer_timestamp: 0,
+            message: String::new(),
+            co_authors: Vec::new(),
+            parents: Vec::new(),
+            file_changes: vec![FileChange {
+                path: "file".to_string(),
+                old_path: None,
+                status,
+                insertions: 1,
+                deletions: 0,
+                is_binary: false,
+                old_size: 0,
+                new_size: 2,
+            }],
+            merge_renames: Vec::new(),
+        }
+    }
+
+    fn file_authors(files: &[(&str, &[&str])]) -> HashMap<String, BTreeSet<String>> {
+        files
+            .iter()
+            .map(|(path, authors)| {
+                let authors = authors.iter().map(|p| p.to_string()).collect();
+                (path.to_string(), authors)
+            })
+            .collect()
+    }
+
+    fn critical_authors(truck_factor: &TruckFactor) -> Vec<(&str, Vec<&str>)> {
+        truck_factor
+            .authors
+            .iter()
+            .map(|p| {
+                (
+                    p.author.as_str(),
+                    p.files.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file_authors_need_a_degree_of_authorship_close_to_the_highest() {
+        // The creator's DOA stays within 75% of a maintainer with 10 later
+        // commits, but not of one with 20.
+        for (changes, authors) in [(10, vec!["A", "B"]), (20, vec!["B"])] {
+            let mut commits = vec![commit("B", FileStatus::Modified); changes];
+            commits.push(commit("A", FileStatus::Added));
+
+            let file_authors = get_file_authors(&commits, Credit::Author);
+
+            let expected: BTreeSet<String> = authors.into_iter().map(String::from).collect();
+            assert_eq!(file_authors["file"], expected);
+        }
+    }
+
+    #[test]
+    fn truck_factor_of_a_single_owner_is_one() {
+        let truck_factor = get_truck_factor(&file_authors(&[("a", &["A"]), ("b", &["A"])]));
+
+        assert_eq!(truck_factor.factor, 1);
+        assert_eq!(critical_authors(&truck_factor), [("A", vec!["a", "b"])]);
+    }
+
+    #[test]
+    fn truck_factor_counts_every_disjoint_owner() {
+        let truck_factor = get_truck_factor(&file_authors(&[
+            ("a", &["A"]),
+            ("b", &["A"]),
+            ("c", &["B"]),
+            ("d", &["B", "C"]),
+        ]));
+
+        // Losing A orphans half the files, which is not yet more than half.
+        assert_eq!(truck_factor.factor, 2);
+        assert_eq!(
+            critical_authors(&truck_factor),
+            [("A", vec!["a", "b"]), ("B", vec!["c"])]
+        );
+    }
+
+    #[test]
+    fn truck_factor_counts_files_orphaned_from_the_start() {
+        let mostly_orphaned = file_authors(&[("a", &[]), ("b", &[]), ("c", &["A"])]);
+        assert_eq!(get_truck_factor(&mostly_orphaned).factor, 0);
+
+        let partly_orphaned = file_authors(&[("a", &[]), ("b", &["A"]), ("c", &["B"])]);
+        let truck_factor = get_truck_factor(&partly_orphaned);
+        assert_eq!(truck_factor.factor, 1);
+        assert_eq!(critical_authors(&truck_factor), [("A", vec!["b"])]);
+    }
+
+    #[test]
+    fn scoped_truck_factors_cover_directories_up_to_depth() {
+        let file_authors = file_authors(&[
+            ("README", &["B"]),
+            ("src/lib.rs", &["A"]),
+            ("src/cli/args.rs", &["A"]),
+            ("docs/guide.md", &["B"]),
+        ]);
+
+        let scopes = get_scoped_truck_factors(&file_authors, 1);
+
+        let summary: Vec<(&str, usize, usize)> = scopes
+            .iter()
+            .map(|(scope, p)| (scope.as_str(), p.files, p.factor))
+            .collect();
+        assert_eq!(summary, [(".", 4, 2), ("docs", 1, 1), ("src", 2, 1)]);
+        assert_eq!(get_scoped_truck_factors(&file_authors, 0).len(), 1);
+        assert_eq!(get_scoped_truck_factors(&file_authors, 2).len(), 4);
+    }
+}
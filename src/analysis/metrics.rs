@@ -1,17 +1,166 @@
-use std::collections::HashMap;
+//! Raw data extraction from commits: ownership, coupling, revision counts,
+//! timestamps and churn.
 
-use crate::repo::{CommitInfo, FileStatus};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::repo::{CommitInfo, FileStatus, LineOwners};
 use itertools::Itertools;
+use regex::Regex;
 use serde::Serialize;
 
+/// Two co-changing files, ordered so that `first < second`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct FilePair {
+    pub first: String,
+    pub second: String,
+}
+
+/// Two authors sharing files, ordered so that `first < second`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct AuthorPair {
+    pub first: String,
+    pub second: String,
+}
+
+/// How a commit with `Co-authored-by:` trailers is credited to its
+/// contributors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Credit {
+    /// Only the author gets credit; trailers are ignored.
+    #[default]
+    Author,
+    /// The author and every co-author each get full credit.
+    Full,
+    /// The author and co-authors share one unit of credit equally.
+    Split,
+}
+
+/// Each author's contribution to one file or directory, in commits or lines
+/// depending on the analysis, see [`get_owners`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Owners {
+    pub authors: HashMap<String, f64>,
+}
+
+impl Owners {
+    /// Contributions of all authors combined.
+    pub fn total(&self) -> f64 {
+        self.authors.values().sum()
+    }
+
+    /// The author with the largest contribution.
+    pub fn primary(&self) -> Option<&String> {
+        self.authors
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|p| p.0)
+    }
+
+    /// The author's fraction of all contributions.
+    pub fn share(&self, author: &str) -> f64 {
+        match self.total() {
+            0.0 => 0.0,
+            total => self.authors.get(author).copied().unwrap_or(0.0) / total,
+        }
+    }
+}
+
+impl From<&LineOwners> for Owners {
+    /// Ownership of a file's current lines, from blame results.
+    fn from(line_owners: &LineOwners) -> Owners {
+        let authors = line_owners
+            .authors
+            .iter()
+            .map(|(author, lines)| (author.clone(), *lines as f64))
+            .collect();
+        Owners { authors }
+    }
+}
+
+/// What ownership is measured by, see [`get_owners_by`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OwnershipMetric {
+    /// Commits touching the file.
+    #[default]
+    Commits,
+    /// Lines added to the file.
+    Added,
+    /// Lines removed from the file, i.e. who refactors and cleans up.
+    Removed,
+}
+
+/// One author's work on one file, see [`get_contributions`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Contribution {
+    pub commits: f64,
+    pub insertions: f64,
+    pub deletions: f64,
+    pub first_change: i64,
+    pub last_change: i64,
+}
+
+/// Lines added and deleted, see [`get_line_changes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LineChanges {
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Bytes added and removed by changes to binary files, see
+/// [`get_byte_changes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ByteChanges {
+    pub added: u64,
+    pub removed: u64,
+}
+
+/// Repository-wide totals returned by [`get_summary`].
 #[derive(Debug, Serialize)]
 pub struct SummaryStats {
     pub commits: usize,
+    pub merges: usize,
     pub files: usize,
     pub file_changes: usize,
     pub authors: usize,
 }
 
+/// Indentation complexity of one file's contents, see [`get_complexity`].
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+pub struct Complexity {
+    pub lines: usize,
+    pub total: usize,
+    pub mean: f64,
+    pub max: usize,
+}
+
+/// Activity recorded under one ticket, see [`get_tickets`].
+#[derive(Debug, Default)]
+pub struct TicketStats {
+    pub commits: usize,
+    pub files: BTreeSet<String>,
+    pub authors: BTreeSet<String>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Work one committer applied, see [`get_integrators`].
+#[derive(Debug, Default)]
+pub struct IntegratorStats {
+    pub commits: usize,
+    /// Commits authored by someone other than the committer.
+    pub integrated: usize,
+    pub authors: BTreeSet<String>,
+}
+
+/// Commits and lines credited to one author, see [`get_author_churn`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AuthorChurn {
+    pub commits: f64,
+    pub insertions: f64,
+    pub deletions: f64,
+}
+
+/// Drops files whose newest change in `commits` deleted them.
 pub fn filter_deleted<V>(files: HashMap<String, V>, commits: &[CommitInfo]) -> HashMap<String, V> {
     let file_statuses = get_file_statuses(commits);
     files
@@ -25,6 +174,7 @@ pub fn filter_deleted<V>(files: HashMap<String, V>, commits: &[CommitInfo]) -> H
         .collect::<HashMap<String, V>>()
 }
 
+/// Repository-wide totals.
 pub fn get_summary(commits: &[CommitInfo]) -> SummaryStats {
     let files = get_file_statuses(commits)
         .iter()
@@ -33,18 +183,22 @@ pub fn get_summary(commits: &[CommitInfo]) -> SummaryStats {
 
     let file_changes = commits.iter().map(|p| p.file_changes.len()).sum();
 
-    let authors = get_user_last_active(commits).len();
+    let authors = commits.iter().map(|p| &p.author).unique().count();
+
+    let merges = commits.iter().filter(|p| p.is_merge()).count();
 
     let commits = commits.len();
 
     SummaryStats {
         commits,
+        merges,
         files,
         file_changes,
         authors,
     }
 }
 
+/// Status of each file's newest change.
 pub fn get_file_statuses(commits: &[CommitInfo]) -> HashMap<String, FileStatus> {
     let mut file_statuses: HashMap<String, FileStatus> = HashMap::new();
 
@@ -59,41 +213,120 @@ pub fn get_file_statuses(commits: &[CommitInfo]) -> HashMap<String, FileStatus>
     file_statuses
 }
 
-pub fn get_owners(commits: &[CommitInfo]) -> HashMap<String, HashMap<String, usize>> {
-    let mut files: HashMap<String, HashMap<String, usize>> = HashMap::new();
+/// The contributors credited for a commit and the share of the commit each
+/// is credited with.
+pub fn get_credits(commit: &CommitInfo, credit: Credit) -> Vec<(&String, f64)> {
+    match credit {
+        Credit::Author => vec![(&commit.author, 1.0)],
+        Credit::Full => commit.contributors().map(|p| (p, 1.0)).collect(),
+        Credit::Split => {
+            let share = 1.0 / (commit.co_authors.len() + 1) as f64;
+            commit.contributors().map(|p| (p, share)).collect()
+        }
+    }
+}
+
+/// Commits each author made to each file, keyed by file then author.
+/// Co-authored commits are credited according to `credit`.
+pub fn get_owners(commits: &[CommitInfo], credit: Credit) -> HashMap<String, Owners> {
+    get_owners_by(commits, OwnershipMetric::Commits, credit)
+}
+
+/// Each author's contribution to each file as measured by `metric`, keyed by
+/// file then author. Authors who contributed nothing by that measure, and
+/// files nobody did, are left out.
+pub fn get_owners_by(
+    commits: &[CommitInfo],
+    metric: OwnershipMetric,
+    credit: Credit,
+) -> HashMap<String, Owners> {
+    let mut files: HashMap<String, Owners> = HashMap::new();
 
     for commit in commits {
-        let author = commit.author_name.clone();
+        let credits = get_credits(commit, credit);
         for file in &commit.file_changes {
-            *files
-                .entry(file.path.clone())
-                .or_default()
-                .entry(author.clone())
-                .or_default() += 1;
+            let contribution = match metric {
+                OwnershipMetric::Commits => 1,
+                OwnershipMetric::Added => file.insertions,
+                OwnershipMetric::Removed => file.deletions,
+            };
+            if contribution == 0 {
+                continue;
+            }
+
+            let owners = &mut files.entry(file.path.clone()).or_default().authors;
+            for (author, share) in &credits {
+                *owners.entry((*author).clone()).or_default() += contribution as f64 * share;
+            }
         }
     }
 
     files
 }
 
-pub fn get_coupling(
+/// Every author's commits, lines added and removed, and first and last change
+/// to each file, keyed by file then author. Co-authored commits are credited
+/// according to `credit`.
+pub fn get_contributions(
     commits: &[CommitInfo],
-    max_changeset_size: usize,
-) -> HashMap<(String, String), usize> {
-    let mut couplings: HashMap<(String, String), usize> = HashMap::new();
+    credit: Credit,
+) -> HashMap<String, HashMap<String, Contribution>> {
+    let mut files: HashMap<String, HashMap<String, Contribution>> = HashMap::new();
 
-    for commit in commits
+    for commit in commits {
+        let credits = get_credits(commit, credit);
+        for file in &commit.file_changes {
+            let authors = files.entry(file.path.clone()).or_default();
+            for (author, share) in &credits {
+                let contribution =
+                    authors
+                        .entry((*author).clone())
+                        .or_insert_with(|| Contribution {
+                            commits: 0.0,
+                            insertions: 0.0,
+                            deletions: 0.0,
+                            first_change: commit.timestamp,
+                            last_change: commit.timestamp,
+                        });
+                contribution.commits += share;
+                contribution.insertions += file.insertions as f64 * share;
+                contribution.deletions += file.deletions as f64 * share;
+                contribution.first_change = contribution.first_change.min(commit.timestamp);
+                contribution.last_change = contribution.last_change.max(commit.timestamp);
+            }
+        }
+    }
+
+    files
+}
+
+/// Sorted file lists of every commit touching at most `max_changeset_size` files.
+pub fn get_changesets(commits: &[CommitInfo], max_changeset_size: usize) -> Vec<Vec<String>> {
+    commits
         .iter()
         .filter(|p| p.file_changes.len() <= max_changeset_size)
-    {
-        let mut changed_files: Vec<String> =
-            commit.file_changes.iter().map(|p| p.path.clone()).collect();
+        .map(|commit| {
+            commit
+                .file_changes
+                .iter()
+                .map(|p| p.path.clone())
+                .sorted()
+                .collect()
+        })
+        .collect()
+}
 
-        changed_files.sort();
+/// Number of changesets in which each pair of files changed together.
+pub fn get_coupling(commits: &[CommitInfo], max_changeset_size: usize) -> HashMap<FilePair, usize> {
+    let mut couplings: HashMap<FilePair, usize> = HashMap::new();
 
+    for changed_files in get_changesets(commits, max_changeset_size) {
         for entry in changed_files.iter().combinations(2) {
             *couplings
-                .entry((entry[0].clone(), entry[1].clone()))
+                .entry(FilePair {
+                    first: entry[0].clone(),
+                    second: entry[1].clone(),
+                })
                 .or_default() += 1;
         }
     }
@@ -101,14 +334,35 @@ pub fn get_coupling(
     couplings
 }
 
-pub fn get_owner_coupling(commits: &[CommitInfo]) -> HashMap<(String, String), usize> {
-    let mut owner_coupling: HashMap<(String, String), usize> = HashMap::new();
-    let file_owners = get_owners(commits);
+/// For each file, the total number of other files it changed together with.
+pub fn get_sum_of_coupling(
+    commits: &[CommitInfo],
+    max_changeset_size: usize,
+) -> HashMap<String, usize> {
+    let mut sum_of_coupling: HashMap<String, usize> = HashMap::new();
+
+    for changed_files in get_changesets(commits, max_changeset_size) {
+        for file in &changed_files {
+            *sum_of_coupling.entry(file.clone()).or_default() += changed_files.len() - 1;
+        }
+    }
+
+    sum_of_coupling
+}
+
+/// Number of files each pair of authors has both changed. Co-authors count
+/// as having changed a file unless `credit` is [`Credit::Author`].
+pub fn get_owner_coupling(commits: &[CommitInfo], credit: Credit) -> HashMap<AuthorPair, usize> {
+    let mut owner_coupling: HashMap<AuthorPair, usize> = HashMap::new();
+    let file_owners = get_owners(commits, credit);
 
     for (_file, owners) in file_owners {
-        for owner_pair in owners.keys().sorted().combinations(2) {
+        for owner_pair in owners.authors.keys().sorted().combinations(2) {
             *owner_coupling
-                .entry((owner_pair[0].clone(), owner_pair[1].clone()))
+                .entry(AuthorPair {
+                    first: owner_pair[0].clone(),
+                    second: owner_pair[1].clone(),
+                })
                 .or_default() += 1;
         }
     }
@@ -116,36 +370,77 @@ pub fn get_owner_coupling(commits: &[CommitInfo]) -> HashMap<(String, String), u
     owner_coupling
 }
 
-pub fn get_primary_owners(
-    file_owners: &HashMap<String, HashMap<String, usize>>,
-) -> HashMap<String, String> {
-    let file_primary_owners: HashMap<String, String> = file_owners
+/// The author with the largest contribution to each file.
+pub fn get_primary_owners(file_owners: &HashMap<String, Owners>) -> HashMap<String, String> {
+    file_owners
         .iter()
-        .map(|p| {
-            (
-                p.0.clone(),
-                p.1.iter()
-                    .max_by_key(|p| p.1)
-                    .expect("iterator was empty")
-                    .0
-                    .clone(),
-            )
-        })
-        .collect();
-    file_primary_owners
+        .filter_map(|(path, owners)| Some((path.clone(), owners.primary()?.clone())))
+        .collect()
 }
 
-pub fn get_user_last_active(commits: &[CommitInfo]) -> HashMap<String, i64> {
+/// Timestamp of each author's newest activity: commits they authored, and
+/// commits they applied as committer, so maintainers who mostly integrate
+/// patches stay active. Co-authors count as active unless `credit` is
+/// [`Credit::Author`].
+pub fn get_user_last_active(commits: &[CommitInfo], credit: Credit) -> HashMap<String, i64> {
     let mut users: HashMap<String, i64> = HashMap::new();
+    let mut record = |user: &String, timestamp: i64| {
+        let last_active = users.entry(user.clone()).or_insert(timestamp);
+        *last_active = (*last_active).max(timestamp);
+    };
 
     for commit in commits {
-        users
-            .entry(commit.author_name.clone())
-            .or_insert(commit.timestamp);
+        for (author, _) in get_credits(commit, credit) {
+            record(author, commit.timestamp);
+        }
+        record(&commit.committer, commit.committer_timestamp);
     }
 
     users
 }
+
+/// Commits each committer applied, and how many of those were authored by
+/// someone else.
+pub fn get_integrators(commits: &[CommitInfo]) -> HashMap<String, IntegratorStats> {
+    let mut integrators: HashMap<String, IntegratorStats> = HashMap::new();
+
+    for commit in commits {
+        let stats = integrators.entry(commit.committer.clone()).or_default();
+        stats.commits += 1;
+        if commit.author != commit.committer {
+            stats.integrated += 1;
+            stats.authors.insert(commit.author.clone());
+        }
+    }
+
+    integrators
+}
+
+/// Commits, lines added and lines deleted per author. Co-authored commits are
+/// credited according to `credit`.
+pub fn get_author_churn(commits: &[CommitInfo], credit: Credit) -> HashMap<String, AuthorChurn> {
+    let mut authors: HashMap<String, AuthorChurn> = HashMap::new();
+
+    for commit in commits {
+        let (insertions, deletions) =
+            commit
+                .file_changes
+                .iter()
+                .fold((0, 0), |(insertions, deletions), file| {
+                    (insertions + file.insertions, deletions + file.deletions)
+                });
+        for (author, share) in get_credits(commit, credit) {
+            let churn = authors.entry(author.clone()).or_default();
+            churn.commits += share;
+            churn.insertions += insertions as f64 * share;
+            churn.deletions += deletions as f64 * share;
+        }
+    }
+
+    authors
+}
+
+/// Number of commits that changed each file.
 pub fn get_revision_counts(commits: &[CommitInfo]) -> HashMap<String, usize> {
     let mut revision_counts: HashMap<String, usize> = HashMap::new();
 
@@ -159,20 +454,38 @@ pub fn get_revision_counts(commits: &[CommitInfo]) -> HashMap<String, usize> {
     revision_counts
 }
 
-pub fn get_line_changes(commits: &[CommitInfo]) -> HashMap<String, (usize, usize)> {
-    let mut line_changes: HashMap<String, (usize, usize)> = HashMap::new();
+/// Lines added and deleted per file.
+pub fn get_line_changes(commits: &[CommitInfo]) -> HashMap<String, LineChanges> {
+    let mut line_changes: HashMap<String, LineChanges> = HashMap::new();
 
     for commit in commits {
         for file in &commit.file_changes {
-            let (insertions, deletions) = line_changes.entry(file.path.clone()).or_insert((0, 0));
-            *insertions += file.insertions;
-            *deletions += file.deletions;
+            let changes = line_changes.entry(file.path.clone()).or_default();
+            changes.insertions += file.insertions;
+            changes.deletions += file.deletions;
         }
     }
 
     line_changes
 }
 
+/// Growth and shrinkage in bytes of binary files, whose line counts are
+/// meaningless. Text files are left out.
+pub fn get_byte_changes(commits: &[CommitInfo]) -> HashMap<String, ByteChanges> {
+    let mut byte_changes: HashMap<String, ByteChanges> = HashMap::new();
+
+    for commit in commits {
+        for file in commit.file_changes.iter().filter(|p| p.is_binary) {
+            let changes = byte_changes.entry(file.path.clone()).or_default();
+            changes.added += file.new_size.saturating_sub(file.old_size);
+            changes.removed += file.old_size.saturating_sub(file.new_size);
+        }
+    }
+
+    byte_changes
+}
+
+/// Timestamp of the newest commit that changed each file.
 pub fn get_files_last_modified(commits: &[CommitInfo]) -> HashMap<String, i64> {
     let mut timestamps: HashMap<String, i64> = HashMap::new();
 
@@ -187,6 +500,7 @@ pub fn get_files_last_modified(commits: &[CommitInfo]) -> HashMap<String, i64> {
     timestamps
 }
 
+/// Timestamp of the oldest commit that changed each file.
 pub fn get_files_creation(commits: &[CommitInfo]) -> HashMap<String, i64> {
     let mut timestamps: HashMap<String, i64> = HashMap::new();
 
@@ -198,3 +512,71 @@ pub fn get_files_creation(commits: &[CommitInfo]) -> HashMap<String, i64> {
 
     timestamps
 }
+
+/// Author of the oldest commit that changed each file.
+pub fn get_file_creators(commits: &[CommitInfo]) -> HashMap<String, String> {
+    let mut creators: HashMap<String, String> = HashMap::new();
+
+    for commit in commits {
+        for file in &commit.file_changes {
+            creators.insert(file.path.clone(), commit.author.clone());
+        }
+    }
+
+    creators
+}
+
+/// Indentation-based complexity: every tab or four spaces of leading
+/// whitespace on a non-blank line counts as one logical indent.
+pub fn get_complexity(contents: &str) -> Complexity {
+    let indents: Vec<usize> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let whitespace = &line[..line.len() - line.trim_start().len()];
+            let tabs = whitespace.chars().filter(|c| *c == '\t').count();
+            let spaces = whitespace.chars().filter(|c| *c == ' ').count();
+            tabs + spaces / 4
+        })
+        .collect();
+
+    let lines = indents.len();
+    let total = indents.iter().sum();
+
+    Complexity {
+        lines,
+        total,
+        mean: if lines == 0 {
+            0.0
+        } else {
+            total as f64 / lines as f64
+        },
+        max: indents.into_iter().max().unwrap_or(0),
+    }
+}
+
+/// Groups commit activity by every ticket ID the pattern finds in commit
+/// messages. A commit mentioning several tickets counts towards each.
+pub fn get_tickets(commits: &[CommitInfo], pattern: &Regex) -> HashMap<String, TicketStats> {
+    let mut tickets: HashMap<String, TicketStats> = HashMap::new();
+
+    for commit in commits {
+        for ticket in pattern
+            .find_iter(&commit.message)
+            .map(|p| p.as_str())
+            .unique()
+        {
+            let stats = tickets.entry(ticket.to_string()).or_default();
+            stats.commits += 1;
+            stats.authors.insert(commit.author.clone());
+
+            for file in &commit.file_changes {
+                stats.files.insert(file.path.clone());
+                stats.insertions += file.insertions;
+                stats.deletions += file.deletions;
+            }
+        }
+    }
+
+    tickets
+}
@@ -0,0 +1,133 @@
+//! On-disk cache of parsed commits, stored in `.git/gitarch/` and keyed by
+//! commit OID. A commit's diff only changes with the attributes that mark
+//! files binary, so cached records stay valid until an attribute file changes;
+//! records of commits no longer reachable from any ref are pruned whenever the
+//! cache is rewritten.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    result::Result,
+};
+
+use git2::{ObjectType, Oid, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::repo::{CommitInfo, MergePolicy, ParseOptions, RepoError};
+
+/// Bumped whenever the layout of cached records changes.
+const CACHE_VERSION: u32 = 6;
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct CommitCache {
+    version: u32,
+    /// Attribute files the records were diffed with, see
+    /// [`attribute_files`].
+    #[serde(default)]
+    attributes: Vec<(String, String)>,
+    commits: HashMap<String, CommitInfo>,
+}
+
+/// Path and blob id of every attribute file the diff reads to tell binary
+/// files apart: `.gitattributes` files as staged in the index,
+/// `.git/info/attributes` and `core.attributesFile`.
+fn attribute_files(repo: &Repository) -> Vec<(String, String)> {
+    let blob_id = |path: &Path| {
+        let contents = fs::read(path).ok()?;
+        Oid::hash_object(ObjectType::Blob, &contents).ok()
+    };
+
+    let mut files: Vec<(String, String)> = Vec::new();
+
+    if let Ok(index) = repo.index() {
+        for entry in index.iter() {
+            let path = String::from_utf8_lossy(&entry.path).to_string();
+            if path != ".gitattributes" && !path.ends_with("/.gitattributes") {
+                continue;
+            }
+            files.push((path, entry.id.to_string()));
+        }
+    }
+
+    let info_attributes = repo.path().join("info").join("attributes");
+    let global_attributes = repo
+        .config()
+        .and_then(|config| config.get_path("core.attributesFile"))
+        .ok();
+    for path in std::iter::once(info_attributes).chain(global_attributes) {
+        if let Some(id) = blob_id(&path) {
+            files.push((path.to_string_lossy().to_string(), id.to_string()));
+        }
+    }
+
+    files
+}
+
+/// Cached records depend on the diff options, so each combination gets its
+/// own file.
+fn cache_path(repo: &Repository, options: &ParseOptions) -> PathBuf {
+    let renames = if options.find_renames {
+        format!("renames{}", options.rename_threshold)
+    } else {
+        "norenames".to_string()
+    };
+    let merges = match options.merges {
+        MergePolicy::Skip => "skip",
+        MergePolicy::FirstParent => "firstparent",
+        MergePolicy::Combined => "combined",
+    };
+
+    repo.path()
+        .join("gitarch")
+        .join(format!("commits-{renames}-{merges}.json"))
+}
+
+impl CommitCache {
+    /// Loads the cache, or an empty one if it is missing, unreadable, was
+    /// written by an incompatible version or with other attribute files.
+    pub(crate) fn load(repo: &Repository, options: &ParseOptions) -> CommitCache {
+        let attributes = attribute_files(repo);
+        let mut cache = fs::read(cache_path(repo, options))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CommitCache>(&bytes).ok())
+            .filter(|cache| cache.version == CACHE_VERSION && cache.attributes == attributes)
+            .unwrap_or_default();
+        cache.attributes = attributes;
+        cache
+    }
+
+    pub(crate) fn get(&self, hash: &str) -> Option<&CommitInfo> {
+        self.commits.get(hash)
+    }
+
+    /// Adds `commits` and drops records unreachable from every ref, then
+    /// writes the cache back to disk.
+    pub(crate) fn save(
+        mut self,
+        repo: &Repository,
+        options: &ParseOptions,
+        commits: &[CommitInfo],
+    ) -> Result<(), RepoError> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_glob("*")?;
+        revwalk.push_head()?;
+        let reachable = revwalk
+            .map(|p| p.map(|p| p.to_string()))
+            .collect::<Result<HashSet<String>, _>>()?;
+
+        self.version = CACHE_VERSION;
+        self.commits.retain(|hash, _| reachable.contains(hash));
+        for commit in commits {
+            self.commits.insert(commit.hash.clone(), commit.clone());
+        }
+
+        let path = cache_path(repo, options);
+        let temp_path = path.with_extension("json.tmp");
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&temp_path, serde_json::to_vec(&self).unwrap())?;
+        fs::rename(temp_path, path)?;
+
+        Ok(())
+    }
+}
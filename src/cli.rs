@@ -1,4 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use gitarch::analysis::metrics::{Credit, OwnershipMetric};
+use gitarch::grouping::ChangesetGrouping;
+use gitarch::repo::{MergePolicy, ParseOptions, WalkOrder};
+use regex::Regex;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -9,6 +13,10 @@ pub struct Cli {
     #[command(subcommand)]
     pub command_type: Commands,
     #[command(flatten)]
+    pub repo_config: RepoConfig,
+    #[command(flatten)]
+    pub grouping_config: GroupingConfig,
+    #[command(flatten)]
     pub config: OutputConfig,
 }
 
@@ -18,16 +26,247 @@ pub enum Commands {
     Coupling {
         #[arg(long, default_value_t = 20)]
         max_changeset_size: usize,
-        #[arg(long, default_value_t = 15)]
-        coupling_percentage: usize,
+        #[command(flatten)]
+        thresholds: CouplingThresholds,
+        #[command(flatten)]
+        changesets: ChangesetConfig,
+    },
+    Soc {
+        #[arg(long, default_value_t = 20)]
+        max_changeset_size: usize,
+        #[command(flatten)]
+        changesets: ChangesetConfig,
     },
     Communication,
-    Ownership,
+    Ownership {
+        #[arg(long, value_enum, default_value_t = Metric::Commits)]
+        metric: Metric,
+        #[arg(long)]
+        detailed: bool,
+    },
+    MainDev,
+    RefactoringMainDev,
+    BlameOwnership {
+        #[arg(long)]
+        rev: Option<String>,
+    },
     Decay {
         #[arg(long, default_value_t = 180)]
         decay_threshold: i64,
+        #[arg(long)]
+        blame: bool,
     },
-    Churn,
+    Churn {
+        #[arg(long)]
+        by_author: bool,
+    },
+    Hotspots,
+    KnowledgeLoss {
+        #[arg(long)]
+        departed: Vec<String>,
+        #[arg(long, default_value_t = 180)]
+        inactive_days: i64,
+        #[arg(long)]
+        blame: bool,
+    },
+    TruckFactor {
+        #[arg(long, default_value_t = 0)]
+        depth: usize,
+    },
+    Integrators,
+    Tickets {
+        #[arg(long, default_value = TICKET_PATTERN, value_parser = Regex::new)]
+        ticket_pattern: Regex,
+    },
+    ComplexityTrend {
+        file: String,
+    },
+}
+
+impl Commands {
+    /// Whether the analysis reads file contents at HEAD, which cannot be
+    /// mapped onto components.
+    pub fn reads_file_contents(&self) -> bool {
+        match self {
+            Commands::Hotspots | Commands::ComplexityTrend { .. } => true,
+            Commands::BlameOwnership { .. } => true,
+            Commands::Decay { blame, .. } | Commands::KnowledgeLoss { blame, .. } => *blame,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct CouplingThresholds {
+    #[arg(long, default_value_t = 15)]
+    pub coupling_percentage: usize,
+    #[arg(long, default_value_t = 1)]
+    pub min_revs: usize,
+    #[arg(long, default_value_t = 1)]
+    pub min_shared_revs: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum GroupBy {
+    Commit,
+    Author,
+    Merge,
+    Ticket,
+}
+
+const TICKET_PATTERN: &str = r"[A-Z]+-\d+|#\d+";
+
+#[derive(Debug, Clone, Parser)]
+pub struct ChangesetConfig {
+    #[arg(long, value_enum, default_value_t = GroupBy::Commit)]
+    pub group_by: GroupBy,
+    #[arg(long, default_value_t = 1)]
+    pub time_window: i64,
+    #[arg(long, default_value = TICKET_PATTERN, value_parser = Regex::new)]
+    pub ticket_pattern: Regex,
+}
+
+impl From<ChangesetConfig> for ChangesetGrouping {
+    fn from(config: ChangesetConfig) -> Self {
+        match config.group_by {
+            GroupBy::Commit => ChangesetGrouping::Commit,
+            GroupBy::Author => ChangesetGrouping::Author {
+                window: config.time_window * 24 * 60 * 60,
+            },
+            GroupBy::Merge => ChangesetGrouping::Merge,
+            GroupBy::Ticket => ChangesetGrouping::Ticket(config.ticket_pattern),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SortOrder {
+    Time,
+    Topo,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Merges {
+    Skip,
+    FirstParent,
+    Combined,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Metric {
+    Commits,
+    Added,
+    Removed,
+}
+
+impl From<Metric> for OwnershipMetric {
+    fn from(metric: Metric) -> Self {
+        match metric {
+            Metric::Commits => OwnershipMetric::Commits,
+            Metric::Added => OwnershipMetric::Added,
+            Metric::Removed => OwnershipMetric::Removed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Role {
+    Author,
+    Committer,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CoAuthorCredit {
+    Author,
+    Full,
+    Split,
+}
+
+impl From<CoAuthorCredit> for Credit {
+    fn from(credit: CoAuthorCredit) -> Self {
+        match credit {
+            CoAuthorCredit::Author => Credit::Author,
+            CoAuthorCredit::Full => Credit::Full,
+            CoAuthorCredit::Split => Credit::Split,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct RepoConfig {
+    #[arg(long)]
+    pub no_renames: bool,
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u16).range(0..=100))]
+    pub rename_threshold: u16,
+    #[arg(long)]
+    pub aliases: Option<PathBuf>,
+    #[arg(long)]
+    pub no_cache: bool,
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    #[arg(long = "rev", allow_hyphen_values = true)]
+    pub revisions: Vec<String>,
+    #[arg(long)]
+    pub all: bool,
+    #[arg(long)]
+    pub first_parent: bool,
+    #[arg(long, value_enum, default_value_t = SortOrder::Time)]
+    pub sort: SortOrder,
+    #[arg(long, value_enum)]
+    pub merges: Option<Merges>,
+}
+
+impl From<&RepoConfig> for ParseOptions {
+    fn from(config: &RepoConfig) -> Self {
+        let merges = config.merges.unwrap_or(if config.first_parent {
+            Merges::FirstParent
+        } else {
+            Merges::Combined
+        });
+
+        ParseOptions::default()
+            .find_renames(!config.no_renames)
+            .rename_threshold(config.rename_threshold)
+            .alias_file(config.aliases.clone())
+            .use_cache(!config.no_cache)
+            .jobs(config.jobs)
+            .revisions(config.revisions.clone())
+            .all_refs(config.all)
+            .first_parent(config.first_parent)
+            .order(match config.sort {
+                SortOrder::Time => WalkOrder::Time,
+                SortOrder::Topo => WalkOrder::Topological,
+            })
+            .merges(match merges {
+                Merges::Skip => MergePolicy::Skip,
+                Merges::FirstParent => MergePolicy::FirstParent,
+                Merges::Combined => MergePolicy::Combined,
+            })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct GroupingConfig {
+    #[arg(long, global = true)]
+    pub include: Vec<String>,
+    #[arg(long, global = true)]
+    pub exclude: Vec<String>,
+    #[arg(long)]
+    pub no_gitattributes: bool,
+    #[arg(long)]
+    pub binaries: bool,
+    #[arg(long)]
+    pub teams: Option<PathBuf>,
+    #[arg(long, requires = "teams")]
+    pub by_team: bool,
+    #[arg(long)]
+    pub components: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = CoAuthorCredit::Author)]
+    pub credit: CoAuthorCredit,
+    #[arg(long, value_enum, default_value_t = Role::Author)]
+    pub identity: Role,
+    #[arg(long, value_enum, default_value_t = Role::Author)]
+    pub time: Role,
 }
 
 #[derive(Debug, Parser)]
@@ -0,0 +1,575 @@
+//! Rewrites parsed commits before analysis: authors to teams, paths to
+//! components, and commits to logical changesets.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    result::Result,
+};
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use thiserror::Error;
+
+use crate::repo::{CommitInfo, FileAttributes, FileChange, FileStatus, LineOwners, split_identity};
+use itertools::Itertools;
+
+/// Errors raised while loading team or component definitions.
+#[derive(Error, Debug)]
+pub enum GroupingError {
+    #[error("Failed to read {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("Invalid entry on line {0} of {1}: expected `<pattern> => <name>`")]
+    Entry(usize, String),
+    #[error("Invalid glob `{0}`: {1}")]
+    Glob(String, glob::PatternError),
+    #[error("Invalid regex `{0}`: {1}")]
+    Regex(String, regex::Error),
+}
+
+/// Parses a `<key> => <value>` definition file. Blank lines and lines starting
+/// with `#` are ignored.
+fn read_definitions(path: &Path) -> Result<Vec<(String, String)>, GroupingError> {
+    let display = path.display().to_string();
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| GroupingError::Io(display.clone(), e))?;
+
+    let mut definitions = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once("=>") {
+            Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                definitions.push((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => return Err(GroupingError::Entry(index + 1, display)),
+        }
+    }
+
+    Ok(definitions)
+}
+
+/// Maps authors to teams. Authors can be listed by full identity
+/// (`Name <email>`), by email, or by name.
+pub struct Teams {
+    members: HashMap<String, String>,
+}
+
+impl Teams {
+    /// Loads an `author => team` definition file.
+    pub fn load(path: &Path) -> Result<Teams, GroupingError> {
+        let members = read_definitions(path)?
+            .into_iter()
+            .map(|(author, team)| (author.to_lowercase(), team))
+            .collect();
+
+        Ok(Teams { members })
+    }
+
+    /// The team the commit's author belongs to, if any.
+    pub fn team_of(&self, commit: &CommitInfo) -> Option<&String> {
+        [&commit.author, &commit.author_email, &commit.author_name]
+            .into_iter()
+            .find_map(|key| self.members.get(&key.to_lowercase()))
+    }
+
+    /// The team a `Name <email>` identity belongs to, if any.
+    pub fn team_of_identity(&self, identity: &str) -> Option<&String> {
+        let (name, email) = split_identity(identity).unwrap_or((identity, identity));
+        [identity, email, name]
+            .into_iter()
+            .find_map(|key| self.members.get(&key.to_lowercase()))
+    }
+}
+
+/// Merges the line counts of authors on the same team, for blame results
+/// from [`read_blame`](crate::repo::read_blame).
+pub fn group_lines_by_team(
+    line_owners: HashMap<String, LineOwners>,
+    teams: &Teams,
+) -> HashMap<String, LineOwners> {
+    line_owners
+        .into_iter()
+        .map(|(path, owners)| {
+            let mut team_owners = LineOwners::default();
+            for (author, lines) in owners.authors {
+                let team = teams.team_of_identity(&author).cloned().unwrap_or(author);
+                *team_owners.authors.entry(team).or_default() += lines;
+            }
+            (path, team_owners)
+        })
+        .collect()
+}
+
+/// Credits each commit to its committer instead of its author, e.g. to
+/// analyse who integrates changes. Co-authors are dropped.
+pub fn use_committer_identity(commits: &mut [CommitInfo]) {
+    for commit in commits.iter_mut() {
+        commit.author = commit.committer.clone();
+        commit.author_name = commit.committer_name.clone();
+        commit.author_email = commit.committer_email.clone();
+        commit.co_authors.clear();
+    }
+}
+
+/// Dates each commit by when it was committed instead of when it was
+/// authored, which rebases and cherry-picks leave far in the past.
+pub fn use_committer_time(commits: &mut [CommitInfo]) {
+    for commit in commits.iter_mut() {
+        commit.timestamp = commit.committer_timestamp;
+    }
+}
+
+/// Replaces each commit's author and co-authors with their team so every
+/// author-keyed analysis runs at team level. Authors without a team keep
+/// their identity.
+pub fn group_by_team(commits: &mut [CommitInfo], teams: &Teams) {
+    for commit in commits.iter_mut() {
+        if let Some(team) = teams.team_of(commit) {
+            commit.author = team.clone();
+        }
+        if let Some(team) = teams.team_of_identity(&commit.committer) {
+            commit.committer = team.clone();
+        }
+
+        let mut co_authors: Vec<String> = Vec::new();
+        for co_author in &commit.co_authors {
+            let co_author = teams.team_of_identity(co_author).unwrap_or(co_author);
+            if *co_author != commit.author && !co_authors.contains(co_author) {
+                co_authors.push(co_author.clone());
+            }
+        }
+        commit.co_authors = co_authors;
+    }
+}
+
+enum PathPattern {
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl PathPattern {
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            PathPattern::Glob(pattern) => pattern.matches_with(
+                path,
+                MatchOptions {
+                    require_literal_separator: true,
+                    ..MatchOptions::new()
+                },
+            ),
+            PathPattern::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// Include and exclude globs applied to every file path. Patterns without a
+/// `/` match the file name in any directory and patterns ending in `/` match
+/// everything below that directory, as in `.gitignore`.
+#[derive(Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+fn gitignore_pattern(pattern: &str) -> Result<Pattern, GroupingError> {
+    let mut glob = pattern.trim_start_matches('/').to_string();
+    if !pattern.trim_end_matches('/').contains('/') {
+        glob = format!("**/{glob}");
+    }
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+
+    Pattern::new(&glob).map_err(|e| GroupingError::Glob(pattern.to_string(), e))
+}
+
+impl PathFilter {
+    /// Builds a filter from command line patterns and, if it exists, an ignore
+    /// file listing one exclude pattern per line.
+    pub fn load(
+        include: &[String],
+        exclude: &[String],
+        ignore_file: Option<&Path>,
+    ) -> Result<PathFilter, GroupingError> {
+        let mut exclude = exclude.to_vec();
+
+        if let Some(ignore_file) = ignore_file.filter(|p| p.exists()) {
+            let contents = std::fs::read_to_string(ignore_file)
+                .map_err(|e| GroupingError::Io(ignore_file.display().to_string(), e))?;
+            exclude.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty() && !p.starts_with('#'))
+                    .map(String::from),
+            );
+        }
+
+        Ok(PathFilter {
+            include: include
+                .iter()
+                .map(|p| gitignore_pattern(p))
+                .collect::<Result<_, _>>()?,
+            exclude: exclude
+                .iter()
+                .map(|p| gitignore_pattern(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the path passes the filter.
+    pub fn matches(&self, path: &str) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches_with(path, options)))
+            && !self.exclude.iter().any(|p| p.matches_with(path, options))
+    }
+}
+
+/// Drops every file change whose path does not pass the filter.
+pub fn filter_paths(commits: &mut [CommitInfo], filter: &PathFilter) {
+    for commit in commits.iter_mut() {
+        commit
+            .file_changes
+            .retain(|file| filter.matches(&file.path));
+    }
+}
+
+/// Drops every change to a binary file.
+pub fn filter_binaries(commits: &mut [CommitInfo]) {
+    for commit in commits.iter_mut() {
+        commit.file_changes.retain(|file| !file.is_binary);
+    }
+}
+
+/// Drops every file change to a file marked generated or vendored in
+/// `.gitattributes`. Changes to files marked binary are flagged as binary and
+/// left to [`filter_binaries`].
+pub fn filter_attributes(commits: &mut [CommitInfo], attributes: &HashMap<String, FileAttributes>) {
+    for commit in commits.iter_mut() {
+        commit.file_changes.retain_mut(|file| {
+            let Some(attributes) = attributes.get(&file.path) else {
+                return true;
+            };
+            file.is_binary |= attributes.binary;
+            !(attributes.generated || attributes.vendored)
+        });
+    }
+}
+
+/// Maps file paths to logical components. Patterns starting with `^` are
+/// regexes, anything else is a glob. The first matching pattern wins.
+pub struct Components {
+    patterns: Vec<(PathPattern, String)>,
+}
+
+impl Components {
+    /// Loads a `pattern => component` definition file.
+    pub fn load(path: &Path) -> Result<Components, GroupingError> {
+        let patterns = read_definitions(path)?
+            .into_iter()
+            .map(|(pattern, component)| {
+                let pattern = if pattern.starts_with('^') {
+                    PathPattern::Regex(
+                        Regex::new(&pattern).map_err(|e| GroupingError::Regex(pattern, e))?,
+                    )
+                } else {
+                    PathPattern::Glob(
+                        Pattern::new(&pattern).map_err(|e| GroupingError::Glob(pattern, e))?,
+                    )
+                };
+                Ok((pattern, component))
+            })
+            .collect::<Result<_, GroupingError>>()?;
+
+        Ok(Components { patterns })
+    }
+
+    /// The component containing the path, if any.
+    pub fn component_of(&self, path: &str) -> Option<&String> {
+        self.patterns
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, component)| component)
+    }
+}
+
+/// Rewrites every file path to its component so file-based analyses run at
+/// component granularity. Files outside every component are dropped, and
+/// changes to several files of one component in a commit count once. A
+/// component is never considered deleted just because one of its files was.
+pub fn group_by_component(commits: &mut [CommitInfo], components: &Components) {
+    for commit in commits.iter_mut() {
+        let mut grouped: HashMap<String, FileChange> = HashMap::new();
+
+        for file in commit.file_changes.drain(..) {
+            let Some(component) = components.component_of(&file.path) else {
+                continue;
+            };
+
+            let status = match file.status {
+                FileStatus::Added => FileStatus::Added,
+                _ => FileStatus::Modified,
+            };
+
+            grouped
+                .entry(component.clone())
+                .and_modify(|change| {
+                    if change.status != status {
+                        change.status = FileStatus::Modified;
+                    }
+                    change.insertions += file.insertions;
+                    change.deletions += file.deletions;
+                })
+                .or_insert(FileChange {
+                    path: component.clone(),
+                    old_path: None,
+                    status,
+                    ..file
+                });
+        }
+
+        commit.file_changes = grouped.into_values().collect();
+    }
+}
+
+/// How commits are combined into logical changesets for coupling analyses.
+#[derive(Clone, Debug)]
+pub enum ChangesetGrouping {
+    /// Every commit is its own changeset.
+    Commit,
+    /// Consecutive commits by the same author less than `window` seconds
+    /// apart form one changeset.
+    Author { window: i64 },
+    /// A merge commit and every commit it brought into the mainline form one
+    /// changeset.
+    Merge,
+    /// Commits whose message mentions the same ticket ID, as matched by the
+    /// pattern, form one changeset. A commit mentioning several tickets joins
+    /// each of their changesets, as in `get_tickets`.
+    Ticket(Regex),
+}
+
+/// Combines commits into logical changesets, each represented as a single
+/// commit carrying the union of its file changes. Expects and returns commits
+/// in newest-first order.
+pub fn group_changesets(commits: &[CommitInfo], grouping: &ChangesetGrouping) -> Vec<CommitInfo> {
+    let groups: Vec<Vec<&CommitInfo>> = match grouping {
+        ChangesetGrouping::Commit => return commits.to_vec(),
+        ChangesetGrouping::Author { window } => group_by_author(commits, *window),
+        ChangesetGrouping::Merge => group_by_merge(commits),
+        ChangesetGrouping::Ticket(pattern) => group_by_ticket(commits, pattern),
+    };
+
+    groups
+        .into_iter()
+        .map(merge_commits)
+        .sorted_by(|a, b| b.timestamp.cmp(&a.timestamp))
+        .collect()
+}
+
+fn group_by_author(commits: &[CommitInfo], window: i64) -> Vec<Vec<&CommitInfo>> {
+    let mut groups: Vec<Vec<&CommitInfo>> = Vec::new();
+    let mut open_groups: HashMap<&String, usize> = HashMap::new();
+
+    for commit in commits.iter().sorted_by_key(|p| p.timestamp) {
+        match open_groups.get(&commit.author) {
+            Some(&index) if commit.timestamp - groups[index].last().unwrap().timestamp < window => {
+                groups[index].push(commit)
+            }
+            _ => {
+                open_groups.insert(&commit.author, groups.len());
+                groups.push(vec![commit]);
+            }
+        }
+    }
+
+    groups
+}
+
+fn group_by_merge(commits: &[CommitInfo]) -> Vec<Vec<&CommitInfo>> {
+    let by_hash: HashMap<&String, &CommitInfo> = commits.iter().map(|p| (&p.hash, p)).collect();
+    let mut assigned: HashSet<&String> = HashSet::new();
+    let mut groups: Vec<Vec<&CommitInfo>> = Vec::new();
+
+    // The first-parent chain of the newest commit is the mainline. Commits it
+    // never reaches, e.g. unmerged branches under `--all`, start mainlines of
+    // their own, so every commit ends up in some changeset.
+    for start in commits {
+        let mut mainline: Vec<&CommitInfo> = Vec::new();
+        let mut next = Some(start);
+        while let Some(commit) = next
+            && assigned.insert(&commit.hash)
+        {
+            mainline.push(commit);
+            next = commit.parents.first().and_then(|p| by_hash.get(p).copied());
+        }
+
+        for commit in mainline.into_iter().rev() {
+            let mut group = vec![commit];
+            let mut pending: Vec<&String> = commit.parents.iter().skip(1).collect();
+
+            while let Some(hash) = pending.pop() {
+                if let Some(branch_commit) = by_hash.get(hash)
+                    && assigned.insert(hash)
+                {
+                    group.push(branch_commit);
+                    pending.extend(&branch_commit.parents);
+                }
+            }
+
+            groups.push(group);
+        }
+    }
+
+    groups
+}
+
+fn group_by_ticket<'a>(commits: &'a [CommitInfo], pattern: &Regex) -> Vec<Vec<&'a CommitInfo>> {
+    let mut groups: Vec<Vec<&CommitInfo>> = Vec::new();
+    let mut tickets: HashMap<&str, usize> = HashMap::new();
+
+    for commit in commits {
+        let mut matched = false;
+        for ticket in pattern
+            .find_iter(&commit.message)
+            .map(|p| p.as_str())
+            .unique()
+        {
+            let index = *tickets.entry(ticket).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[index].push(commit);
+            matched = true;
+        }
+
+        if !matched {
+            groups.push(vec![commit]);
+        }
+    }
+
+    groups
+}
+
+/// Folds a group of commits into one, keeping the newest commit's metadata
+/// and the newest status of every file.
+fn merge_commits(group: Vec<&CommitInfo>) -> CommitInfo {
+    let newest = *group.iter().max_by_key(|p| p.timestamp).unwrap();
+    let mut file_changes: Vec<FileChange> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for commit in group.iter().sorted_by(|a, b| b.timestamp.cmp(&a.timestamp)) {
+        for file in &commit.file_changes {
+            match indices.get(&file.path) {
+                Some(&index) => {
+                    file_changes[index].insertions += file.insertions;
+                    file_changes[index].deletions += file.deletions;
+                }
+                None => {
+                    indices.insert(file.path.clone(), file_changes.len());
+                    file_changes.push(file.clone());
+                }
+            }
+        }
+    }
+
+    CommitInfo {
+        file_changes,
+        ..newest.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(hash: &str, parents: &[&str], timestamp: i64) -> CommitInfo {
+        CommitInfo {
+            hash: hash.to_string(),
+            author: "A <a@x>".to_string(),
+            author_name: "A".to_string(),
+            author_email: "a@x".to_string(),
+            timestamp,
+            committer: "A <a@x>".to_string(),
+            committer_name: "A".to_string(),
+            committer_email: "a@x".to_string(),
+            committer_timestamp: timestamp,
+            message: hash.to_string(),
+            co_authors: Vec::new(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            file_changes: vec![FileChange {
+                path: format!("{hash}.txt"),
+                old_path: None,
+                status: FileStatus::Added,
+                insertions: 1,
+                deletions: 0,
+                is_binary: false,
+                old_size: 0,
+                new_size: 2,
+            }],
+            merge_renames: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn gitignore_patterns_match_like_gitignore() {
+        let filter = |pattern: &str| PathFilter::load(&[], &[pattern.to_string()], None).unwrap();
+        let excluded = |pattern: &str, path: &str| !filter(pattern).matches(path);
+
+        // Without a `/`, a pattern matches the file name in any directory.
+        assert!(excluded("*.lock", "Cargo.lock"));
+        assert!(excluded("*.lock", "web/yarn.lock"));
+        assert!(!excluded("*.lock", "Cargo.lock.md"));
+
+        // A trailing `/` matches everything below the directory, wherever it is.
+        assert!(excluded("vendor/", "vendor/lib.rs"));
+        assert!(excluded("vendor/", "web/vendor/deep/lib.js"));
+        assert!(!excluded("vendor/", "vendored.rs"));
+
+        // Any other `/` anchors the pattern at the repository root.
+        assert!(excluded("/build/", "build/out.o"));
+        assert!(!excluded("/build/", "src/build/out.o"));
+        assert!(excluded("src/*.rs", "src/main.rs"));
+        assert!(!excluded("src/*.rs", "src/cli/args.rs"));
+        assert!(!excluded("src/*.rs", "lib/src/main.rs"));
+    }
+
+    #[test]
+    fn group_by_merge_keeps_unmerged_branches() {
+        // The newest commit sits on a branch forked before the merge, so the
+        // merge is not on its first-parent chain.
+        let commits = vec![
+            commit("unmerged", &["base"], 5),
+            commit("merge", &["main", "feature"], 4),
+            commit("main", &["base"], 3),
+            commit("feature", &["base"], 2),
+            commit("base", &[], 1),
+        ];
+
+        let changesets = group_changesets(&commits, &ChangesetGrouping::Merge);
+
+        let files: HashSet<&str> = changesets
+            .iter()
+            .flat_map(|p| &p.file_changes)
+            .map(|p| p.path.as_str())
+            .collect();
+        assert_eq!(files.len(), commits.len());
+
+        let merge = changesets.iter().find(|p| p.hash == "merge").unwrap();
+        let merged: HashSet<&str> = merge.file_changes.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(merged, HashSet::from(["merge.txt", "feature.txt"]));
+    }
+}
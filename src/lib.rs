@@ -0,0 +1,25 @@
+//! Extracts implicit knowledge from git history: ownership, coupling, decay,
+//! churn and hotspots.
+//!
+//! [`repo`] reads commits out of a repository, [`grouping`] optionally rewrites
+//! them (teams, components, logical changesets), and [`analysis`] turns them
+//! into results. Results keyed by file path use the path as it exists at the
+//! newest analysed commit.
+//!
+//! ```no_run
+//! use gitarch::analysis::derived::get_decay;
+//! use gitarch::analysis::metrics::{Credit, get_coupling};
+//! use gitarch::repo::{ParseOptions, follow_renames, parse_commit_info};
+//!
+//! let mut commits = parse_commit_info(".".as_ref(), ParseOptions::default())?;
+//! follow_renames(&mut commits);
+//!
+//! let coupling = get_coupling(&commits, 20);
+//! let decay = get_decay(&commits, 180, Credit::Author, None);
+//! # Ok::<(), gitarch::repo::RepoError>(())
+//! ```
+
+pub mod analysis;
+mod cache;
+pub mod grouping;
+pub mod repo;
@@ -1,19 +1,19 @@
-mod analysis;
 mod cli;
 mod output;
-mod repo;
 
 use anyhow::Context;
 
 use chrono::{DateTime, NaiveDate};
 
-use crate::{cli::*, output::*, repo::*};
+use gitarch::{analysis::metrics::get_revision_counts, grouping::*, repo::*};
+
+use crate::{cli::*, output::*};
 
 use clap::Parser;
 
 fn main() {
     if let Err(e) = run() {
-        eprintln!("error: {e}");
+        eprintln!("error: {e:#}");
         std::process::exit(1);
     }
 }
@@ -21,9 +21,85 @@ fn main() {
 fn run() -> anyhow::Result<()> {
     let command = Cli::parse();
 
+    if command.grouping_config.components.is_some() && command.command_type.reads_file_contents() {
+        anyhow::bail!("--components cannot be used with analyses of file contents");
+    }
+
     let config = command.config;
 
-    let commits = parse_commit_info(&command.repo).context("Failed to read respository")?;
+    let repo_config = command.repo_config;
+
+    let mut commits = parse_commit_info(&command.repo, (&repo_config).into())
+        .context("Failed to read respository")?;
+
+    if !repo_config.no_renames {
+        follow_renames(&mut commits);
+    }
+
+    let grouping_config = command.grouping_config;
+
+    let ignore_file = find_workdir(&command.repo)
+        .context("Failed to read respository")?
+        .map(|p| p.join(".gitarchignore"));
+    let path_filter = PathFilter::load(
+        &grouping_config.include,
+        &grouping_config.exclude,
+        ignore_file.as_deref(),
+    )
+    .context("Failed to load path filters")?;
+
+    if !path_filter.is_empty() {
+        filter_paths(&mut commits, &path_filter);
+    }
+
+    if !grouping_config.no_gitattributes {
+        let files = get_revision_counts(&commits).into_keys();
+        let attributes =
+            read_attributes(&command.repo, files).context("Failed to read .gitattributes")?;
+        filter_attributes(&mut commits, &attributes);
+    }
+
+    // Churn reports binaries by their byte churn instead of lines.
+    if !grouping_config.binaries && !matches!(command.command_type, Commands::Churn { .. }) {
+        filter_binaries(&mut commits);
+    }
+
+    if grouping_config.identity == Role::Committer {
+        use_committer_identity(&mut commits);
+    }
+
+    if grouping_config.time == Role::Committer {
+        use_committer_time(&mut commits);
+    }
+
+    let teams = match &grouping_config.teams {
+        Some(teams) if grouping_config.by_team => {
+            Some(Teams::load(teams).context("Failed to load team definitions")?)
+        }
+        _ => None,
+    };
+
+    if let Some(teams) = &teams {
+        group_by_team(&mut commits, teams);
+    }
+
+    let read_line_owners = |rev: Option<&str>, commits: &[CommitInfo]| {
+        let files = get_revision_counts(commits).into_keys();
+        read_blame(&command.repo, files, rev, &(&repo_config).into())
+            .context("Failed to blame files")
+            .map(|line_owners| match &teams {
+                Some(teams) => group_lines_by_team(line_owners, teams),
+                None => line_owners,
+            })
+    };
+
+    if let Some(components) = &grouping_config.components {
+        let components =
+            Components::load(components).context("Failed to load component definitions")?;
+        group_by_component(&mut commits, &components);
+    }
+
+    let credit = grouping_config.credit.into();
 
     let filtered_commits: Vec<CommitInfo> = commits
         .iter()
@@ -38,21 +114,96 @@ fn run() -> anyhow::Result<()> {
 
     match command.command_type {
         Commands::Summary => print_summary(&filtered_commits, config),
-        Commands::Decay { decay_threshold } => {
-            print_decay(&filtered_commits, decay_threshold, config)
+        Commands::Decay {
+            decay_threshold,
+            blame,
+        } => {
+            let line_owners = match blame {
+                true => Some(read_line_owners(None, &filtered_commits)?),
+                false => None,
+            };
+            print_decay(
+                &filtered_commits,
+                decay_threshold,
+                credit,
+                line_owners.as_ref(),
+                config,
+            )
         }
         Commands::Coupling {
             max_changeset_size,
-            coupling_percentage,
+            thresholds,
+            changesets,
         } => print_coupling(
-            &filtered_commits,
+            &group_changesets(&filtered_commits, &changesets.into()),
+            max_changeset_size,
+            thresholds,
+            config,
+        ),
+        Commands::Soc {
+            max_changeset_size,
+            changesets,
+        } => print_sum_of_coupling(
+            &group_changesets(&filtered_commits, &changesets.into()),
             max_changeset_size,
-            coupling_percentage,
             config,
         ),
-        Commands::Ownership => print_owners(&filtered_commits, config),
-        Commands::Communication => print_communication(&filtered_commits, config),
-        Commands::Churn => print_churn(&commits, &filtered_commits, config),
+        Commands::Ownership {
+            metric,
+            detailed: true,
+        } => print_detailed_owners(&filtered_commits, metric.into(), credit, config),
+        Commands::Ownership { metric, .. } => {
+            print_owners(&filtered_commits, metric.into(), credit, config)
+        }
+        Commands::MainDev => print_owners(&filtered_commits, Metric::Added.into(), credit, config),
+        Commands::RefactoringMainDev => {
+            print_owners(&filtered_commits, Metric::Removed.into(), credit, config)
+        }
+        Commands::BlameOwnership { rev } => {
+            let line_owners = read_line_owners(rev.as_deref(), &filtered_commits)?;
+            print_blame_ownership(&line_owners, config)
+        }
+        Commands::Communication => print_communication(&filtered_commits, credit, config),
+        Commands::Churn { by_author: true } => {
+            print_author_churn(&filtered_commits, credit, config)
+        }
+        Commands::Churn { .. } => print_churn(&commits, &filtered_commits, config),
+        Commands::KnowledgeLoss {
+            departed,
+            inactive_days,
+            blame,
+        } => {
+            let line_owners = match blame {
+                true => Some(read_line_owners(None, &filtered_commits)?),
+                false => None,
+            };
+            print_knowledge_loss(
+                &filtered_commits,
+                &departed,
+                inactive_days,
+                credit,
+                line_owners.as_ref(),
+                config,
+            )
+        }
+        Commands::TruckFactor { depth } => {
+            print_truck_factor(&filtered_commits, depth, credit, config)
+        }
+        Commands::Integrators => print_integrators(&filtered_commits, config),
+        Commands::Hotspots => {
+            let files = get_revision_counts(&filtered_commits).into_keys();
+            let contents =
+                read_head_contents(&command.repo, files).context("Failed to read files at HEAD")?;
+            print_hotspots(&filtered_commits, &contents, config)
+        }
+        Commands::Tickets { ticket_pattern } => {
+            print_tickets(&filtered_commits, &ticket_pattern, config)
+        }
+        Commands::ComplexityTrend { file } => {
+            let revisions = read_file_history(&command.repo, &filtered_commits, &file)
+                .context("Failed to read file history")?;
+            print_complexity_trend(&revisions, config)
+        }
     };
 
     Ok(())
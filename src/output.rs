@@ -1,9 +1,11 @@
-use crate::analysis::{derived::*, metrics::*};
 use crate::*;
 use cliux::Table;
+use gitarch::analysis::{derived::*, metrics::*};
 use itertools::Itertools;
+use regex::Regex;
 use serde::Serialize;
 use serde_json::to_string_pretty;
+use std::collections::{BTreeSet, HashMap};
 
 #[derive(Serialize)]
 struct DecayEntry {
@@ -15,12 +17,54 @@ struct DecayEntry {
 struct CouplingEntry {
     file_pair: (String, String),
     count: usize,
+    revisions: (usize, usize),
+    average_revisions: f64,
+    degree: f64,
+    support: f64,
+    confidence: (f64, f64),
 }
 
 #[derive(Serialize)]
 struct OwnershipEntry {
     file: String,
     owner: String,
+    contribution: f64,
+    total: f64,
+    share: f64,
+}
+
+#[derive(Serialize)]
+struct DetailedOwnershipEntry {
+    file: String,
+    fractal_value: f64,
+    authors: Vec<AuthorOwnership>,
+}
+
+#[derive(Serialize)]
+struct AuthorOwnership {
+    author: String,
+    commits: f64,
+    insertions: f64,
+    deletions: f64,
+    share: f64,
+    first_change: NaiveDate,
+    last_change: NaiveDate,
+}
+
+#[derive(Serialize)]
+struct BlameOwnershipEntry {
+    file: String,
+    lines: usize,
+    owner: String,
+    share: f64,
+    authors: Vec<AuthorLines>,
+}
+
+#[derive(Serialize)]
+struct AuthorLines {
+    author: String,
+    lines: usize,
+    share: f64,
 }
 
 #[derive(Serialize)]
@@ -35,9 +79,82 @@ struct ChurnEntry {
     revisions: usize,
     insertions: usize,
     deletions: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<ByteChanges>,
     created: NaiveDate,
     last_modified: NaiveDate,
 }
+#[derive(Serialize)]
+struct AuthorChurnEntry {
+    author: String,
+    commits: f64,
+    insertions: f64,
+    deletions: f64,
+}
+
+#[derive(Serialize)]
+struct HotspotEntry {
+    file: String,
+    revisions: usize,
+    lines: usize,
+    complexity: usize,
+    score: f64,
+}
+
+#[derive(Serialize)]
+struct ComplexityTrendEntry {
+    commit: String,
+    date: NaiveDate,
+    lines: usize,
+    total: usize,
+    mean: f64,
+    max: usize,
+}
+
+#[derive(Serialize)]
+struct SumOfCouplingEntry {
+    file: String,
+    soc: usize,
+}
+
+#[derive(Serialize)]
+struct TicketEntry {
+    ticket: String,
+    commits: usize,
+    files: Vec<String>,
+    authors: Vec<String>,
+    insertions: usize,
+    deletions: usize,
+}
+
+#[derive(Serialize)]
+struct TruckFactorEntry {
+    scope: String,
+    files: usize,
+    truck_factor: usize,
+    authors: Vec<CriticalAuthor>,
+}
+
+#[derive(Serialize)]
+struct KnowledgeLossEntry {
+    path: String,
+    directory: bool,
+    owner: String,
+    owner_departed: bool,
+    departed: Vec<String>,
+    history_lost: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines_lost: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct IntegratorEntry {
+    committer: String,
+    commits: usize,
+    integrated: usize,
+    authors: Vec<String>,
+}
+
 pub fn print_summary(commits: &[CommitInfo], config: OutputConfig) {
     let summary = get_summary(commits);
 
@@ -47,15 +164,17 @@ pub fn print_summary(commits: &[CommitInfo], config: OutputConfig) {
     } else {
         let SummaryStats {
             commits,
+            merges,
             files,
             file_changes,
             authors,
         } = summary;
 
         let table = Table::new()
-            .headers(&["Commits", "Files", "File Changes", "Authors"])
+            .headers(&["Commits", "Merges", "Files", "File Changes", "Authors"])
             .row(&[
                 &commits.to_string(),
+                &merges.to_string(),
                 &files.to_string(),
                 &file_changes.to_string(),
                 &authors.to_string(),
@@ -64,12 +183,21 @@ pub fn print_summary(commits: &[CommitInfo], config: OutputConfig) {
     }
 }
 
-pub fn print_decay(commits: &[CommitInfo], decay_threshold: i64, config: OutputConfig) {
-    let decay = get_decay(commits, decay_threshold);
+pub fn print_decay(
+    commits: &[CommitInfo],
+    decay_threshold: i64,
+    credit: Credit,
+    line_owners: Option<&HashMap<String, LineOwners>>,
+    config: OutputConfig,
+) {
+    let decay = get_decay(commits, decay_threshold, credit, line_owners);
     let decay = filter_deleted(decay, commits);
     let decay = decay
         .into_iter()
-        .map(|(file, score)| DecayEntry { file, score })
+        .map(|(file, decay)| DecayEntry {
+            file,
+            score: decay.score,
+        })
         .sorted_by(|a, b| b.score.total_cmp(&a.score))
         .take(config.top.unwrap_or(usize::MAX));
 
@@ -91,39 +219,74 @@ pub fn print_decay(commits: &[CommitInfo], decay_threshold: i64, config: OutputC
 pub fn print_coupling(
     commits: &[CommitInfo],
     max_changeset_size: usize,
-    coupling_percent: usize,
+    thresholds: CouplingThresholds,
     config: OutputConfig,
 ) {
-    let coupling = get_coupling(commits, max_changeset_size);
-    let revisions = get_revision_counts(commits);
+    let coupling = get_coupling_stats(commits, max_changeset_size);
 
     let file_statuses = get_file_statuses(commits);
 
     let coupling = coupling
         .into_iter()
-        .filter(|p| {
-            p.1 > ((revisions.get(&p.0.0).unwrap() + revisions.get(&p.0.1).unwrap()) / 2
-                * coupling_percent
-                / 100)
+        .filter(|(_, stats)| {
+            stats.degree >= thresholds.coupling_percentage as f64
+                && stats.revisions.first.min(stats.revisions.second) >= thresholds.min_revs
+                && stats.shared_revisions >= thresholds.min_shared_revs
         })
         .filter(|p| {
-            file_statuses.get(&p.0.0) != Some(&FileStatus::Deleted)
-                && file_statuses.get(&p.0.1) != Some(&FileStatus::Deleted)
+            file_statuses.get(&p.0.first) != Some(&FileStatus::Deleted)
+                && file_statuses.get(&p.0.second) != Some(&FileStatus::Deleted)
+        })
+        .sorted_by(|(_, stats1), (_, stats2)| {
+            stats2
+                .shared_revisions
+                .cmp(&stats1.shared_revisions)
+                .then(stats2.degree.total_cmp(&stats1.degree))
+        })
+        .map(|(file_pair, stats)| CouplingEntry {
+            file_pair: (file_pair.first, file_pair.second),
+            count: stats.shared_revisions,
+            revisions: (stats.revisions.first, stats.revisions.second),
+            average_revisions: stats.average_revisions,
+            degree: stats.degree,
+            support: stats.support,
+            confidence: (stats.confidence.first, stats.confidence.second),
         })
-        .sorted_by(|(_, coupling1), (_, coupling2)| coupling2.cmp(coupling1))
-        .map(|(file_pair, count)| CouplingEntry { file_pair, count })
         .take(config.top.unwrap_or(usize::MAX));
 
     if config.json {
         let json = serde_json::to_string_pretty(&coupling.collect::<Vec<CouplingEntry>>()).unwrap();
         println!("{json}");
     } else {
-        let mut table = Table::new().headers(&["File Pair", "Coupling"]);
+        let mut table = Table::new().headers(&[
+            "File Pair",
+            "Coupling",
+            "Revisions",
+            "Avg Revisions",
+            "Degree (%)",
+            "Support",
+            "Confidence",
+        ]);
 
-        for CouplingEntry { file_pair, count } in coupling {
+        for CouplingEntry {
+            file_pair,
+            count,
+            revisions,
+            average_revisions,
+            degree,
+            support,
+            confidence,
+        } in coupling
+        {
+            let round = |value: f64| (value * 100.0).round() / 100.0;
             table = table.row(&[
                 &format!("{} and {}", file_pair.0, file_pair.1),
                 &count.to_string(),
+                &format!("{} / {}", revisions.0, revisions.1),
+                &round(average_revisions).to_string(),
+                &round(degree).to_string(),
+                &round(support).to_string(),
+                &format!("{} / {}", round(confidence.0), round(confidence.1)),
             ])
         }
 
@@ -131,34 +294,234 @@ pub fn print_coupling(
     }
 }
 
-pub fn print_owners(commits: &[CommitInfo], config: OutputConfig) {
-    let owners = get_primary_owners(&get_owners(commits));
+pub fn print_sum_of_coupling(
+    commits: &[CommitInfo],
+    max_changeset_size: usize,
+    config: OutputConfig,
+) {
+    let sum_of_coupling = get_sum_of_coupling(commits, max_changeset_size);
+    let sum_of_coupling = filter_deleted(sum_of_coupling, commits)
+        .into_iter()
+        .filter(|(_, soc)| *soc > 0)
+        .sorted_by(|(file1, soc1), (file2, soc2)| soc2.cmp(soc1).then(file1.cmp(file2)))
+        .map(|(file, soc)| SumOfCouplingEntry { file, soc })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&sum_of_coupling.collect::<Vec<SumOfCouplingEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&["File", "Sum of Coupling"]);
+
+        for SumOfCouplingEntry { file, soc } in sum_of_coupling {
+            table = table.row(&[&file, &soc.to_string()]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_owners(
+    commits: &[CommitInfo],
+    metric: OwnershipMetric,
+    credit: Credit,
+    config: OutputConfig,
+) {
+    let file_owners = get_owners_by(commits, metric, credit);
+    let owners = get_primary_owners(&file_owners);
     let owners = filter_deleted(owners, commits)
         .into_iter()
         .sorted_by(|a, b| a.0.cmp(&b.0))
-        .map(|(file, owner)| OwnershipEntry { file, owner })
+        .map(|(file, owner)| {
+            let owners = &file_owners[&file];
+            OwnershipEntry {
+                contribution: owners.authors[&owner],
+                total: owners.total(),
+                share: owners.share(&owner),
+                file,
+                owner,
+            }
+        })
         .take(config.top.unwrap_or(usize::MAX));
 
     if config.json {
         let json = to_string_pretty(&owners.collect::<Vec<OwnershipEntry>>()).unwrap();
         println!("{json}");
     } else {
-        let mut table = Table::new().headers(&["File", "Owner"]);
+        let contribution = match metric {
+            OwnershipMetric::Commits => "Commits",
+            OwnershipMetric::Added => "Added",
+            OwnershipMetric::Removed => "Removed",
+        };
+        let mut table = Table::new().headers(&["File", "Owner", contribution, "Total", "Share"]);
+
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        for OwnershipEntry {
+            file,
+            owner,
+            contribution,
+            total,
+            share,
+        } in owners
+        {
+            table = table.row(&[
+                &file,
+                &owner,
+                &round(contribution).to_string(),
+                &round(total).to_string(),
+                &round(share).to_string(),
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_detailed_owners(
+    commits: &[CommitInfo],
+    metric: OwnershipMetric,
+    credit: Credit,
+    config: OutputConfig,
+) {
+    let file_owners = get_owners_by(commits, metric, credit);
+    let fractal_values = get_fractal_values(&file_owners);
+    let date = |timestamp: i64| DateTime::from_timestamp(timestamp, 0).unwrap().date_naive();
+
+    let entries = filter_deleted(get_contributions(commits, credit), commits)
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|(file, contributions)| {
+            let owners = file_owners.get(&file);
+            let authors = contributions
+                .into_iter()
+                .map(|(author, contribution)| AuthorOwnership {
+                    share: owners.map_or(0.0, |p| p.share(&author)),
+                    author,
+                    commits: contribution.commits,
+                    insertions: contribution.insertions,
+                    deletions: contribution.deletions,
+                    first_change: date(contribution.first_change),
+                    last_change: date(contribution.last_change),
+                })
+                .sorted_by(|a, b| b.share.total_cmp(&a.share).then(a.author.cmp(&b.author)))
+                .collect();
+            DetailedOwnershipEntry {
+                fractal_value: fractal_values.get(&file).copied().unwrap_or(0.0),
+                file,
+                authors,
+            }
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&entries.collect::<Vec<DetailedOwnershipEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&[
+            "File",
+            "Fractal Value",
+            "Author",
+            "Commits",
+            "Insertions",
+            "Deletions",
+            "Share",
+            "First Change",
+            "Last Change",
+        ]);
+
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        for DetailedOwnershipEntry {
+            file,
+            fractal_value,
+            authors,
+        } in entries
+        {
+            for (index, author) in authors.into_iter().enumerate() {
+                // File columns are only filled on each file's first row.
+                let (file, fractal_value) = match index {
+                    0 => (file.clone(), round(fractal_value).to_string()),
+                    _ => (String::new(), String::new()),
+                };
+                table = table.row(&[
+                    &file,
+                    &fractal_value,
+                    &author.author,
+                    &round(author.commits).to_string(),
+                    &round(author.insertions).to_string(),
+                    &round(author.deletions).to_string(),
+                    &round(author.share).to_string(),
+                    &author.first_change.to_string(),
+                    &author.last_change.to_string(),
+                ]);
+            }
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_blame_ownership(line_owners: &HashMap<String, LineOwners>, config: OutputConfig) {
+    let entries = line_owners
+        .iter()
+        .sorted_by(|a, b| a.0.cmp(b.0))
+        .map(|(file, owners)| {
+            let lines = owners.total();
+            let authors: Vec<AuthorLines> = owners
+                .authors
+                .iter()
+                .sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)))
+                .map(|(author, author_lines)| AuthorLines {
+                    author: author.clone(),
+                    lines: *author_lines,
+                    share: *author_lines as f64 / lines as f64,
+                })
+                .collect();
+            BlameOwnershipEntry {
+                file: file.clone(),
+                lines,
+                owner: authors[0].author.clone(),
+                share: authors[0].share,
+                authors,
+            }
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&entries.collect::<Vec<BlameOwnershipEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&["File", "Lines", "Owner", "Share", "Authors"]);
 
-        for OwnershipEntry { file, owner } in owners {
-            table = table.row(&[&file, &owner]);
+        for BlameOwnershipEntry {
+            file,
+            lines,
+            owner,
+            share,
+            authors,
+        } in entries
+        {
+            table = table.row(&[
+                &file,
+                &lines.to_string(),
+                &owner,
+                &((share * 100.0).round() / 100.0).to_string(),
+                &authors.len().to_string(),
+            ]);
         }
 
         table.print();
     }
 }
 
-pub fn print_communication(commits: &[CommitInfo], config: OutputConfig) {
-    let owner_coupling = get_owner_coupling(commits);
+pub fn print_communication(commits: &[CommitInfo], credit: Credit, config: OutputConfig) {
+    let owner_coupling = get_owner_coupling(commits, credit);
     let owner_coupling = owner_coupling
         .into_iter()
         .sorted_by(|(_, coupling1), (_, coupling2)| coupling2.cmp(coupling1))
-        .map(|(owner_pair, count)| CommunicationEntry { owner_pair, count })
+        .map(|(owner_pair, count)| CommunicationEntry {
+            owner_pair: (owner_pair.first, owner_pair.second),
+            count,
+        })
         .take(config.top.unwrap_or(usize::MAX));
 
     if config.json {
@@ -188,10 +551,18 @@ pub fn print_churn(commits: &[CommitInfo], filtered_commits: &[CommitInfo], conf
         .sorted_by(|(file, _), (file2, _)| file.cmp(file2))
         .take(config.top.unwrap_or(usize::MAX));
     let revisions = get_revision_counts(filtered_commits);
+    let byte_changes = get_byte_changes(filtered_commits);
 
     let mut churn_entries: Vec<ChurnEntry> = Vec::new();
 
-    for (file, (insertions, deletions)) in line_changes {
+    for (
+        file,
+        LineChanges {
+            insertions,
+            deletions,
+        },
+    ) in line_changes
+    {
         let revisions = *revisions.get(&file).unwrap();
         let created = DateTime::from_timestamp(*created.get(&file).unwrap(), 0)
             .unwrap()
@@ -200,6 +571,7 @@ pub fn print_churn(commits: &[CommitInfo], filtered_commits: &[CommitInfo], conf
             .unwrap()
             .date_naive();
         churn_entries.push(ChurnEntry {
+            bytes: byte_changes.get(&file).copied(),
             file,
             revisions,
             insertions,
@@ -213,32 +585,448 @@ pub fn print_churn(commits: &[CommitInfo], filtered_commits: &[CommitInfo], conf
         let json = to_string_pretty(&churn_entries).unwrap();
         println!("{json}");
     } else {
-        let mut table = Table::new().headers(&[
-            "File",
-            "Revisions",
-            "Insertions",
-            "Deletions",
-            "Created",
-            "Last Modified",
-        ]);
+        let show_bytes = churn_entries.iter().any(|p| p.bytes.is_some());
+        let mut headers = vec!["File", "Revisions", "Insertions", "Deletions"];
+        if show_bytes {
+            headers.extend(["Bytes Added", "Bytes Removed"]);
+        }
+        headers.extend(["Created", "Last Modified"]);
+
+        let mut table = Table::new().headers(&headers);
 
         for ChurnEntry {
             file,
             revisions,
             insertions,
             deletions,
+            bytes,
             created,
             last_modified,
         } in churn_entries
+        {
+            let mut row = vec![
+                file,
+                revisions.to_string(),
+                insertions.to_string(),
+                deletions.to_string(),
+            ];
+            if show_bytes {
+                row.extend(match bytes {
+                    Some(ByteChanges { added, removed }) => {
+                        [added.to_string(), removed.to_string()]
+                    }
+                    None => ["-".to_string(), "-".to_string()],
+                });
+            }
+            row.extend([created.to_string(), last_modified.to_string()]);
+
+            table = table.row(&row.iter().map(String::as_str).collect::<Vec<&str>>());
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_author_churn(commits: &[CommitInfo], credit: Credit, config: OutputConfig) {
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    let authors = get_author_churn(commits, credit)
+        .into_iter()
+        .sorted_by(|(author1, churn1), (author2, churn2)| {
+            (churn2.insertions + churn2.deletions)
+                .total_cmp(&(churn1.insertions + churn1.deletions))
+                .then(author1.cmp(author2))
+        })
+        .map(|(author, churn)| AuthorChurnEntry {
+            author,
+            commits: round(churn.commits),
+            insertions: round(churn.insertions),
+            deletions: round(churn.deletions),
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&authors.collect::<Vec<AuthorChurnEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&["Author", "Commits", "Insertions", "Deletions"]);
+
+        for AuthorChurnEntry {
+            author,
+            commits,
+            insertions,
+            deletions,
+        } in authors
         {
+            table = table.row(&[
+                &author,
+                &commits.to_string(),
+                &insertions.to_string(),
+                &deletions.to_string(),
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_hotspots(
+    commits: &[CommitInfo],
+    contents: &HashMap<String, String>,
+    config: OutputConfig,
+) {
+    let revisions = get_revision_counts(commits);
+    let complexities: HashMap<String, Complexity> = contents
+        .iter()
+        .map(|(file, contents)| (file.clone(), get_complexity(contents)))
+        .collect();
+
+    let hotspots = get_hotspots(&revisions, &complexities)
+        .into_iter()
+        .map(|(file, score)| HotspotEntry {
+            revisions: *revisions.get(&file).unwrap(),
+            lines: complexities.get(&file).unwrap().lines,
+            complexity: complexities.get(&file).unwrap().total,
+            file,
+            score,
+        })
+        .sorted_by(|a, b| b.score.total_cmp(&a.score))
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&hotspots.collect::<Vec<HotspotEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table =
+            Table::new().headers(&["File", "Revisions", "Lines", "Complexity", "Hotspot Score"]);
+
+        for HotspotEntry {
+            file,
+            revisions,
+            lines,
+            complexity,
+            score,
+        } in hotspots
+        {
+            let score = (score * 100.0).round() / 100.0;
             table = table.row(&[
                 &file,
                 &revisions.to_string(),
+                &lines.to_string(),
+                &complexity.to_string(),
+                &score.to_string(),
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_complexity_trend(revisions: &[FileRevision], config: OutputConfig) {
+    let trend = revisions
+        .iter()
+        .rev()
+        .map(|revision| {
+            let Complexity {
+                lines,
+                total,
+                mean,
+                max,
+            } = get_complexity(&revision.contents);
+
+            ComplexityTrendEntry {
+                commit: revision.hash.clone(),
+                date: DateTime::from_timestamp(revision.timestamp, 0)
+                    .unwrap()
+                    .date_naive(),
+                lines,
+                total,
+                mean,
+                max,
+            }
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&trend.collect::<Vec<ComplexityTrendEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&["Commit", "Date", "Lines", "Total", "Mean", "Max"]);
+
+        for ComplexityTrendEntry {
+            commit,
+            date,
+            lines,
+            total,
+            mean,
+            max,
+        } in trend
+        {
+            let mean = (mean * 100.0).round() / 100.0;
+            table = table.row(&[
+                &commit[..commit.len().min(8)],
+                &date.to_string(),
+                &lines.to_string(),
+                &total.to_string(),
+                &mean.to_string(),
+                &max.to_string(),
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_tickets(commits: &[CommitInfo], pattern: &Regex, config: OutputConfig) {
+    let tickets = get_tickets(commits, pattern)
+        .into_iter()
+        .sorted_by(|(ticket1, stats1), (ticket2, stats2)| {
+            stats2
+                .commits
+                .cmp(&stats1.commits)
+                .then(ticket1.cmp(ticket2))
+        })
+        .map(|(ticket, stats)| TicketEntry {
+            ticket,
+            commits: stats.commits,
+            files: stats.files.into_iter().collect(),
+            authors: stats.authors.into_iter().collect(),
+            insertions: stats.insertions,
+            deletions: stats.deletions,
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&tickets.collect::<Vec<TicketEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&[
+            "Ticket",
+            "Commits",
+            "Files",
+            "Authors",
+            "Insertions",
+            "Deletions",
+        ]);
+
+        for TicketEntry {
+            ticket,
+            commits,
+            files,
+            authors,
+            insertions,
+            deletions,
+        } in tickets
+        {
+            table = table.row(&[
+                &ticket,
+                &commits.to_string(),
+                &files.len().to_string(),
+                &authors.join(", "),
                 &insertions.to_string(),
                 &deletions.to_string(),
-                &created.to_string(),
-                &last_modified.to_string(),
-            ])
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_truck_factor(
+    commits: &[CommitInfo],
+    depth: usize,
+    credit: Credit,
+    config: OutputConfig,
+) {
+    let file_authors = filter_deleted(get_file_authors(commits, credit), commits);
+
+    let entries = get_scoped_truck_factors(&file_authors, depth)
+        .into_iter()
+        .map(|(scope, truck_factor)| TruckFactorEntry {
+            scope,
+            files: truck_factor.files,
+            truck_factor: truck_factor.factor,
+            authors: truck_factor.authors,
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&entries.collect::<Vec<TruckFactorEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table =
+            Table::new().headers(&["Scope", "Files", "Truck Factor", "Critical Authors"]);
+
+        for TruckFactorEntry {
+            scope,
+            files,
+            truck_factor,
+            authors,
+        } in entries
+        {
+            let authors = authors
+                .iter()
+                .map(|p| format!("{} ({} files)", p.author, p.files.len()))
+                .join(", ");
+            table = table.row(&[
+                &scope,
+                &files.to_string(),
+                &truck_factor.to_string(),
+                &authors,
+            ]);
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_knowledge_loss(
+    commits: &[CommitInfo],
+    departed: &[String],
+    inactive_days: i64,
+    credit: Credit,
+    line_owners: Option<&HashMap<String, LineOwners>>,
+    config: OutputConfig,
+) {
+    // Departed authors can be given by full identity, email or name;
+    // otherwise everyone inactive for `inactive_days` counts as departed.
+    let departed: BTreeSet<String> = if departed.is_empty() {
+        get_inactive_authors(commits, inactive_days, credit)
+    } else {
+        let departed: Vec<String> = departed.iter().map(|p| p.to_lowercase()).collect();
+        get_user_last_active(commits, credit)
+            .into_keys()
+            .filter(|author| {
+                let (name, email) = split_identity(author).unwrap_or((author, author));
+                [author.as_str(), email, name]
+                    .iter()
+                    .any(|key| departed.contains(&key.to_lowercase()))
+            })
+            .collect()
+    };
+
+    let file_owners = filter_deleted(get_owners(commits, credit), commits);
+    let mut history_loss = get_knowledge_loss(&file_owners, &departed);
+    let directory_loss = get_knowledge_loss(&get_directory_owners(&file_owners), &departed);
+
+    let line_loss = line_owners.map(|line_owners| {
+        let line_owners: HashMap<String, Owners> = line_owners
+            .iter()
+            .map(|(path, owners)| (path.clone(), owners.into()))
+            .collect();
+        let mut line_loss = get_knowledge_loss(&line_owners, &departed);
+        line_loss.extend(get_knowledge_loss(
+            &get_directory_owners(&line_owners),
+            &departed,
+        ));
+        line_loss
+    });
+
+    let directories: BTreeSet<String> = directory_loss.keys().cloned().collect();
+    history_loss.extend(directory_loss);
+
+    let entries = history_loss
+        .into_iter()
+        .map(|(path, loss)| KnowledgeLossEntry {
+            directory: directories.contains(&path),
+            lines_lost: line_loss
+                .as_ref()
+                .map(|p| p.get(&path).map_or(0.0, |p| p.lost)),
+            path,
+            owner: loss.owner,
+            owner_departed: loss.owner_departed,
+            departed: loss.departed.into_iter().collect(),
+            history_lost: loss.lost,
+        })
+        .sorted_by(|a, b| {
+            b.owner_departed
+                .cmp(&a.owner_departed)
+                .then(b.history_lost.total_cmp(&a.history_lost))
+                .then(a.path.cmp(&b.path))
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&entries.collect::<Vec<KnowledgeLossEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut headers = vec![
+            "Path",
+            "Owner",
+            "Owner Departed",
+            "Departed",
+            "History Lost",
+        ];
+        if line_loss.is_some() {
+            headers.push("Lines Lost");
+        }
+        let mut table = Table::new().headers(&headers);
+
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        for KnowledgeLossEntry {
+            path,
+            directory,
+            owner,
+            owner_departed,
+            departed,
+            history_lost,
+            lines_lost,
+        } in entries
+        {
+            let mut row = vec![
+                if directory { format!("{path}/") } else { path },
+                owner,
+                owner_departed.to_string(),
+                departed.join(", "),
+                round(history_lost).to_string(),
+            ];
+            if let Some(lines_lost) = lines_lost {
+                row.push(round(lines_lost).to_string());
+            }
+
+            table = table.row(&row.iter().map(String::as_str).collect::<Vec<&str>>());
+        }
+
+        table.print();
+    }
+}
+
+pub fn print_integrators(commits: &[CommitInfo], config: OutputConfig) {
+    let integrators = get_integrators(commits)
+        .into_iter()
+        .filter(|(_, stats)| stats.integrated > 0)
+        .sorted_by(|(committer1, stats1), (committer2, stats2)| {
+            stats2
+                .integrated
+                .cmp(&stats1.integrated)
+                .then(committer1.cmp(committer2))
+        })
+        .map(|(committer, stats)| IntegratorEntry {
+            committer,
+            commits: stats.commits,
+            integrated: stats.integrated,
+            authors: stats.authors.into_iter().collect(),
+        })
+        .take(config.top.unwrap_or(usize::MAX));
+
+    if config.json {
+        let json = to_string_pretty(&integrators.collect::<Vec<IntegratorEntry>>()).unwrap();
+        println!("{json}");
+    } else {
+        let mut table = Table::new().headers(&["Committer", "Commits", "Integrated", "Authors"]);
+
+        for IntegratorEntry {
+            committer,
+            commits,
+            integrated,
+            authors,
+        } in integrators
+        {
+            table = table.row(&[
+                &committer,
+                &commits.to_string(),
+                &integrated.to_string(),
+                &authors.len().to_string(),
+            ]);
         }
 
         table.print();
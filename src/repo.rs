@@ -1,34 +1,95 @@
-use std::{collections::HashMap, path::Path, result::Result};
+//! git2 data access layer.
 
-use git2::{Delta, Repository};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    result::Result,
+};
+
+use git2::{
+    AttrCheckFlags, AttrValue, BlameOptions, Commit, Delta, DiffFile, DiffFindOptions, Oid,
+    Repository, RevparseMode, Revwalk, Sort, Tree,
+};
+use rayon::{ThreadPoolBuilder, prelude::*};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use thiserror::Error;
 
+use crate::cache::CommitCache;
+
+/// Errors raised while reading a repository.
 #[derive(Error, Debug)]
 pub enum RepoError {
-    #[error("Git error: {0}")]
+    #[error("Git error")]
     Git(#[from] git2::Error),
+    #[error("Failed to read alias file")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid alias entry on line {0}: {1}")]
+    Alias(usize, String),
+    #[error("Failed to start worker threads")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
 }
 
-#[derive(Clone)]
+/// One commit and the files it changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct CommitInfo {
     pub hash: String,
+    /// Canonical identity used to key authors across all analyses.
+    pub author: String,
     pub author_name: String,
     pub author_email: String,
     pub timestamp: i64,
+    /// Canonical identity of whoever applied the commit, which differs from
+    /// the author for rebased, cherry-picked and patch-applied commits.
+    pub committer: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub committer_timestamp: i64,
     pub message: String,
+    /// Canonical identities from the message's `Co-authored-by:` trailers,
+    /// excluding the author.
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+    pub parents: Vec<String>,
     pub file_changes: Vec<FileChange>,
+    /// For merges, the files renamed between each parent and the merge, old
+    /// path to new, so renames made on one branch reach the history of the
+    /// others. Empty for other commits.
+    #[serde(default)]
+    pub merge_renames: Vec<HashMap<String, String>>,
+}
+
+impl CommitInfo {
+    /// Whether the commit has more than one parent.
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+
+    /// The author followed by every co-author.
+    pub fn contributors(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.author).chain(&self.co_authors)
+    }
 }
 
-#[derive(Clone)]
+/// One file changed by a commit, with its line counts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct FileChange {
     pub path: String,
+    pub old_path: Option<String>,
     pub status: FileStatus,
     pub insertions: usize,
     pub deletions: usize,
+    pub is_binary: bool,
+    /// Blob size in bytes before the change, 0 if the file was added.
+    pub old_size: u64,
+    /// Blob size in bytes after the change, 0 if the file was deleted.
+    pub new_size: u64,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// Kind of change made to a file.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FileStatus {
     Added,
     Modified,
@@ -51,86 +112,1063 @@ impl From<Delta> for FileStatus {
     }
 }
 
-pub fn parse_commit_info(path: &Path) -> Result<Vec<CommitInfo>, RepoError> {
+/// Order in which commits are walked. Both always list a commit before its
+/// parents, which the analyses rely on.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WalkOrder {
+    /// Newest commit first by commit time.
+    #[default]
+    Time,
+    /// Commits of a branch kept together, like `git log --topo-order`.
+    Topological,
+}
+
+/// How merge commits are diffed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MergePolicy {
+    /// Merges contribute no file changes.
+    Skip,
+    /// Diff against the first parent, attributing everything the merge
+    /// brought in to the merge commit.
+    FirstParent,
+    /// Only files whose merged contents differ from what git's automatic
+    /// merge produces, i.e. conflict resolutions and edits made while
+    /// merging.
+    #[default]
+    Combined,
+}
+
+/// Options for [`parse_commit_info`]. Start from
+/// [`ParseOptions::default`] and change options with the setters, e.g.
+/// `ParseOptions::default().first_parent(true)`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    pub find_renames: bool,
+    pub rename_threshold: u16,
+    pub alias_file: Option<PathBuf>,
+    /// Reuse and update the commit cache in `.git/gitarch/`.
+    pub use_cache: bool,
+    /// Number of threads diffing commits; defaults to one per CPU.
+    pub jobs: Option<usize>,
+    /// Revisions to walk in git syntax (`main`, `v1.0..v2.0`, `a...b`,
+    /// `^excluded`); HEAD when empty.
+    pub revisions: Vec<String>,
+    /// Walk every ref in addition to `revisions`.
+    pub all_refs: bool,
+    /// Follow only the first parent of merge commits.
+    pub first_parent: bool,
+    pub order: WalkOrder,
+    pub merges: MergePolicy,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            find_renames: true,
+            rename_threshold: 50,
+            alias_file: None,
+            use_cache: false,
+            jobs: None,
+            revisions: Vec::new(),
+            all_refs: false,
+            first_parent: false,
+            order: WalkOrder::Time,
+            merges: MergePolicy::Combined,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn find_renames(mut self, find_renames: bool) -> Self {
+        self.find_renames = find_renames;
+        self
+    }
+
+    pub fn rename_threshold(mut self, rename_threshold: u16) -> Self {
+        self.rename_threshold = rename_threshold;
+        self
+    }
+
+    pub fn alias_file(mut self, alias_file: Option<PathBuf>) -> Self {
+        self.alias_file = alias_file;
+        self
+    }
+
+    pub fn use_cache(mut self, use_cache: bool) -> Self {
+        self.use_cache = use_cache;
+        self
+    }
+
+    pub fn jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    pub fn revisions(mut self, revisions: Vec<String>) -> Self {
+        self.revisions = revisions;
+        self
+    }
+
+    pub fn all_refs(mut self, all_refs: bool) -> Self {
+        self.all_refs = all_refs;
+        self
+    }
+
+    pub fn first_parent(mut self, first_parent: bool) -> Self {
+        self.first_parent = first_parent;
+        self
+    }
+
+    pub fn order(mut self, order: WalkOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn merges(mut self, merges: MergePolicy) -> Self {
+        self.merges = merges;
+        self
+    }
+}
+
+/// Identity rewrites from `.mailmap` and the alias file. Emails, and names
+/// given alongside them, match case-insensitively like they do in git.
+#[derive(Debug, Default)]
+struct Mailmap {
+    /// Proper name and email, keyed by lowercased commit email and, for
+    /// entries that only apply to one name, lowercased commit name.
+    entries: HashMap<(String, Option<String>), MailmapEntry>,
+}
+
+/// Replacements of one mailmap entry; `None` keeps the commit's own value.
+#[derive(Debug, Default)]
+struct MailmapEntry {
+    name: Option<String>,
+    email: Option<String>,
+}
+
+impl Mailmap {
+    /// Adds one line in `.mailmap` syntax, returning `false` if it is
+    /// malformed.
+    fn add_line(&mut self, line: &str) -> bool {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            return true;
+        }
+
+        let mut entries: Vec<(Option<&str>, &str)> = Vec::new();
+        let mut rest = line;
+        while let Some(start) = rest.find('<') {
+            let Some(end) = rest[start..].find('>') else {
+                return false;
+            };
+            let name = rest[..start].trim();
+            let email = rest[start + 1..start + end].trim();
+            entries.push(((!name.is_empty()).then_some(name), email));
+            rest = &rest[start + end + 1..];
+        }
+
+        let (real_name, real_email, replace_name, replace_email) = match entries[..] {
+            [(real_name, replace_email)] => (real_name, None, None, replace_email),
+            [(real_name, real_email), (replace_name, replace_email)] => {
+                (real_name, Some(real_email), replace_name, replace_email)
+            }
+            _ => return false,
+        };
+
+        let key = (
+            replace_email.to_lowercase(),
+            replace_name.map(str::to_lowercase),
+        );
+        let entry = self.entries.entry(key).or_default();
+        if let Some(real_name) = real_name {
+            entry.name = Some(real_name.to_string());
+        }
+        if let Some(real_email) = real_email {
+            entry.email = Some(real_email.to_string());
+        }
+
+        true
+    }
+
+    /// The proper name and lowercased email for an identity. Entries for
+    /// both the name and email take precedence over entries for the email.
+    fn resolve(&self, name: &str, email: &str) -> (String, String) {
+        let email_key = email.to_lowercase();
+        let (real_name, real_email) = self
+            .entries
+            .get(&(email_key.clone(), Some(name.to_lowercase())))
+            .or_else(|| self.entries.get(&(email_key, None)))
+            .map_or((None, None), |entry| {
+                (entry.name.as_deref(), entry.email.as_deref())
+            });
+
+        (
+            real_name.unwrap_or(name).to_string(),
+            real_email.unwrap_or(email).to_lowercase(),
+        )
+    }
+}
+
+/// Loads the repository's mailmap the way git does, from `.mailmap`,
+/// `mailmap.blob` (`HEAD:.mailmap` in bare repositories) and `mailmap.file`,
+/// and layers the entries of the alias file on top. The alias file uses the
+/// same syntax as `.mailmap`.
+fn load_mailmap(repo: &Repository, alias_file: Option<&Path>) -> Result<Mailmap, RepoError> {
+    let mut mailmap = Mailmap::default();
+    let config = repo.config()?;
+
+    let mut sources: Vec<String> = Vec::new();
+    if let Some(workdir) = repo.workdir() {
+        sources.extend(std::fs::read_to_string(workdir.join(".mailmap")).ok());
+    }
+    let blob = config
+        .get_string("mailmap.blob")
+        .ok()
+        .or_else(|| repo.is_bare().then(|| "HEAD:.mailmap".to_string()));
+    if let Some(blob) = blob.and_then(|p| repo.revparse_single(&p).ok()?.peel_to_blob().ok()) {
+        sources.push(String::from_utf8_lossy(blob.content()).to_string());
+    }
+    if let Ok(file) = config.get_path("mailmap.file") {
+        sources.extend(std::fs::read_to_string(file).ok());
+    }
+
+    // Like git, skip what cannot be parsed in the repository's own mailmaps.
+    for line in sources.iter().flat_map(|p| p.lines()) {
+        mailmap.add_line(line);
+    }
+
+    let Some(alias_file) = alias_file else {
+        return Ok(mailmap);
+    };
+
+    for (index, line) in std::fs::read_to_string(alias_file)?.lines().enumerate() {
+        if !mailmap.add_line(line) {
+            return Err(RepoError::Alias(index + 1, line.trim().to_string()));
+        }
+    }
+
+    Ok(mailmap)
+}
+
+/// Adds one revision in git syntax to the walk: a single revision, an
+/// exclusion (`^rev`), a range (`a..b`) or a symmetric difference (`a...b`).
+fn push_revision(repo: &Repository, revwalk: &mut Revwalk, spec: &str) -> Result<(), RepoError> {
+    if let Some(excluded) = spec.strip_prefix('^') {
+        revwalk.hide(repo.revparse_single(excluded)?.peel_to_commit()?.id())?;
+        return Ok(());
+    }
+
+    let revspec = repo.revparse(spec)?;
+    let from = revspec.from().map(|p| p.peel_to_commit()).transpose()?;
+    let to = revspec.to().map(|p| p.peel_to_commit()).transpose()?;
+
+    if revspec.mode().contains(RevparseMode::SINGLE) {
+        if let Some(from) = from {
+            revwalk.push(from.id())?;
+        }
+        return Ok(());
+    }
+
+    let head = repo.head()?.peel_to_commit()?;
+    let from = from.unwrap_or_else(|| head.clone());
+    let to = to.unwrap_or(head);
+
+    if revspec.mode().contains(RevparseMode::MERGE_BASE) {
+        revwalk.push(from.id())?;
+        revwalk.push(to.id())?;
+        revwalk.hide(repo.merge_base(from.id(), to.id())?)?;
+    } else {
+        revwalk.hide(from.id())?;
+        revwalk.push(to.id())?;
+    }
+
+    Ok(())
+}
+
+/// Walks the history selected by the options and extracts every commit,
+/// newest first.
+pub fn parse_commit_info(path: &Path, options: ParseOptions) -> Result<Vec<CommitInfo>, RepoError> {
     let repo = Repository::discover(path)?;
-    let mut commits: Vec<CommitInfo> = Vec::new();
+    let mailmap = load_mailmap(&repo, options.alias_file.as_deref())?;
+    let cache = match options.use_cache {
+        true => CommitCache::load(&repo, &options),
+        false => CommitCache::default(),
+    };
 
     let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
+    revwalk.set_sorting(match options.order {
+        WalkOrder::Time => Sort::TOPOLOGICAL | Sort::TIME,
+        WalkOrder::Topological => Sort::TOPOLOGICAL,
+    })?;
+
+    if options.first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+
+    if options.all_refs {
+        revwalk.push_glob("*")?;
+        revwalk.push_head()?;
+    }
+
+    for spec in &options.revisions {
+        push_revision(&repo, &mut revwalk, spec)?;
+    }
+
+    if !options.all_refs && options.revisions.iter().all(|p| p.starts_with('^')) {
+        revwalk.push_head()?;
+    }
+
+    let hashes = revwalk.collect::<Result<Vec<Oid>, _>>()?;
+
+    let misses: Vec<Oid> = hashes
+        .iter()
+        .filter(|hash| cache.get(&hash.to_string()).is_none())
+        .copied()
+        .collect();
 
-    for latest_commit_hash in revwalk {
-        let hash = latest_commit_hash?;
-        let commit = repo.find_commit(hash)?;
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or(0))
+        .build()?;
 
-        let file_changes = RefCell::new(HashMap::new());
+    // git2 repositories cannot be shared across threads, so every worker opens
+    // its own handle.
+    let git_dir = repo.path();
+    let diffed = pool.install(|| {
+        misses
+            .par_iter()
+            .map_init(
+                || Repository::open(git_dir),
+                |worker_repo, hash| {
+                    let worker_repo = worker_repo
+                        .as_ref()
+                        .map_err(|e| git2::Error::new(e.code(), e.class(), e.message()))?;
+                    diff_commit(worker_repo, &worker_repo.find_commit(*hash)?, &options)
+                },
+            )
+            .collect::<Result<Vec<CommitInfo>, RepoError>>()
+    })?;
 
-        let tree = commit.tree()?;
-        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let cache_misses = diffed.len();
+    let mut diffed = diffed.into_iter();
+    let mut commits: Vec<CommitInfo> = hashes
+        .iter()
+        .map(|hash| match cache.get(&hash.to_string()) {
+            Some(commit_info) => commit_info.clone(),
+            None => diffed.next().unwrap(),
+        })
+        .collect();
 
-        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    // The cache only speeds up later runs, so failing to write it is not fatal.
+    if options.use_cache && cache_misses > 0 {
+        cache.save(&repo, &options, &commits).ok();
+    }
+
+    for commit in &mut commits {
+        resolve_author(&mailmap, commit);
+    }
+
+    Ok(commits)
+}
+
+/// Extracts a commit and its file changes. Merge commits are diffed according
+/// to `options.merges`. Authors are left as recorded in the commit, see
+/// [`resolve_author`].
+fn diff_commit(
+    repo: &Repository,
+    commit: &Commit,
+    options: &ParseOptions,
+) -> Result<CommitInfo, RepoError> {
+    let tree = commit.tree()?;
+    let is_merge = commit.parent_count() > 1;
+    let mut merge_renames = Vec::new();
+
+    let file_changes = match (is_merge, options.merges) {
+        (true, MergePolicy::Combined) => {
+            let parent_diffs = commit
+                .parents()
+                .map(|parent| diff_trees(repo, Some(&parent.tree()?), &tree, options))
+                .collect::<Result<Vec<_>, RepoError>>()?;
+            merge_renames = parent_diffs.iter().map(|p| get_renames(p)).collect();
+
+            drop_clean_merges(repo, commit, &tree, combine_diffs(parent_diffs))?
+        }
+        (true, MergePolicy::Skip) => Vec::new(),
+        _ => {
+            let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+            diff_trees(repo, parent_tree.as_ref(), &tree, options)?
+        }
+    };
+
+    if is_merge && merge_renames.is_empty() && options.find_renames {
+        merge_renames = commit
+            .parents()
+            .map(|parent| find_renames(repo, &parent.tree()?, &tree, options))
+            .collect::<Result<Vec<_>, RepoError>>()?;
+    }
+
+    let commit_info = commit.author();
+    let (author_name, author_email, timestamp) = (
+        commit_info.name().unwrap_or("").to_string(),
+        commit_info.email().unwrap_or("").to_string(),
+        commit_info.when().seconds(),
+    );
+    let committer_info = commit.committer();
+    let (committer_name, committer_email, committer_timestamp) = (
+        committer_info.name().unwrap_or("").to_string(),
+        committer_info.email().unwrap_or("").to_string(),
+        committer_info.when().seconds(),
+    );
+
+    Ok(CommitInfo {
+        hash: commit.id().to_string(),
+        author: format!("{author_name} <{author_email}>"),
+        author_name,
+        author_email,
+        timestamp,
+        committer: format!("{committer_name} <{committer_email}>"),
+        committer_name,
+        committer_email,
+        committer_timestamp,
+        message: commit.message().unwrap_or("").to_string(),
+        co_authors: Vec::new(),
+        parents: commit.parent_ids().map(|p| p.to_string()).collect(),
+        file_changes,
+        merge_renames,
+    })
+}
+
+/// Old path to new path of every renamed file in a diff.
+fn get_renames(file_changes: &[FileChange]) -> HashMap<String, String> {
+    file_changes
+        .iter()
+        .filter(|p| p.status == FileStatus::Renamed)
+        .filter_map(|p| Some((p.old_path.clone()?, p.path.clone())))
+        .collect()
+}
+
+/// Finds the files renamed between two trees without counting lines.
+fn find_renames(
+    repo: &Repository,
+    parent_tree: &Tree,
+    tree: &Tree,
+    options: &ParseOptions,
+) -> Result<HashMap<String, String>, RepoError> {
+    let mut diff = repo.diff_tree_to_tree(Some(parent_tree), Some(tree), None)?;
+    diff.find_similar(Some(
+        DiffFindOptions::new()
+            .renames(true)
+            .rename_threshold(options.rename_threshold),
+    ))?;
 
-        let current_file = RefCell::new("".to_string());
+    Ok(diff
+        .deltas()
+        .filter(|delta| delta.status() == Delta::Renamed)
+        .filter_map(|delta| {
+            let old_path = delta.old_file().path()?.to_string_lossy().to_string();
+            let new_path = delta.new_file().path()?.to_string_lossy().to_string();
+            Some((old_path, new_path))
+        })
+        .collect())
+}
 
-        diff.foreach(
-            &mut |diff_delta, _progress| {
-                let Some(path) = diff_delta.new_file().path() else {
-                    return true;
-                };
+/// Keeps only the files a merge changed relative to every parent, i.e. the
+/// files whose merged contents had to be resolved by hand. Each keeps the
+/// smallest of its per-parent changes.
+fn combine_diffs(parent_diffs: Vec<Vec<FileChange>>) -> Vec<FileChange> {
+    let parent_count = parent_diffs.len();
+    let mut changes: HashMap<String, (usize, FileChange)> = HashMap::new();
 
-                let path = path.to_string_lossy().to_string();
-                *current_file.borrow_mut() = path.clone();
+    for file in parent_diffs.into_iter().flatten() {
+        changes
+            .entry(file.path.clone())
+            .and_modify(|(count, change)| {
+                *count += 1;
+                if file.insertions + file.deletions < change.insertions + change.deletions {
+                    *change = file.clone();
+                }
+            })
+            .or_insert((1, file));
+    }
 
-                let status: FileStatus = diff_delta.status().into();
+    changes
+        .into_values()
+        .filter(|(count, _)| *count == parent_count)
+        .map(|(_, change)| change)
+        .collect()
+}
+
+/// Drops the files of a two-parent merge that git merges cleanly on its own,
+/// which differ from both parents without any work by the merge author.
+/// Octopus merges and merges of unrelated histories are left as they are.
+fn drop_clean_merges(
+    repo: &Repository,
+    commit: &Commit,
+    tree: &Tree,
+    mut file_changes: Vec<FileChange>,
+) -> Result<Vec<FileChange>, RepoError> {
+    if commit.parent_count() != 2 || file_changes.is_empty() {
+        return Ok(file_changes);
+    }
+
+    let (ours, theirs) = (commit.parent(0)?, commit.parent(1)?);
+    let Ok(base) = repo.merge_base(ours.id(), theirs.id()) else {
+        return Ok(file_changes);
+    };
+    let base_tree = repo.find_commit(base)?.tree()?;
+    let merged = repo.merge_trees(&base_tree, &ours.tree()?, &theirs.tree()?, None)?;
+
+    file_changes.retain(|file| {
+        let path = Path::new(&file.path);
+        let conflicted = (1..=3).any(|stage| merged.get_path(path, stage).is_some());
+        let merged_id = merged.get_path(path, 0).map(|p| p.id);
+        let result_id = tree.get_path(path).ok().map(|p| p.id());
+        conflicted || merged_id != result_id
+    });
+
+    Ok(file_changes)
+}
 
-                file_changes.borrow_mut().insert(path, (status, 0, 0));
+/// Diffs two trees and counts the lines added and deleted per file.
+fn diff_trees(
+    repo: &Repository,
+    parent_tree: Option<&Tree>,
+    tree: &Tree,
+    options: &ParseOptions,
+) -> Result<Vec<FileChange>, RepoError> {
+    let file_changes = RefCell::new(HashMap::new());
 
-                true
+    let mut diff = repo.diff_tree_to_tree(parent_tree, Some(tree), None)?;
+
+    if options.find_renames {
+        diff.find_similar(Some(
+            DiffFindOptions::new()
+                .renames(true)
+                .copies(true)
+                .rename_threshold(options.rename_threshold)
+                .copy_threshold(options.rename_threshold),
+        ))?;
+    }
+
+    // Deltas only carry sizes of blobs the diff loaded, which it skips for
+    // files marked binary by `.gitattributes`.
+    let odb = repo.odb()?;
+    let blob_size = |file: DiffFile| match file.size() {
+        0 if !file.id().is_zero() => odb
+            .read_header(file.id())
+            .map_or(0, |(size, _)| size as u64),
+        size => size,
+    };
+
+    let current_file = RefCell::new("".to_string());
+
+    diff.foreach(
+        &mut |diff_delta, _progress| {
+            let Some(path) = diff_delta.new_file().path() else {
+                return true;
+            };
+
+            let path = path.to_string_lossy().to_string();
+            *current_file.borrow_mut() = path.clone();
+
+            let status: FileStatus = diff_delta.status().into();
+
+            let old_path = match status {
+                FileStatus::Renamed | FileStatus::Copied => diff_delta
+                    .old_file()
+                    .path()
+                    .map(|p| p.to_string_lossy().to_string()),
+                _ => None,
+            };
+
+            file_changes.borrow_mut().insert(
+                path.clone(),
+                FileChange {
+                    path,
+                    old_path,
+                    status,
+                    insertions: 0,
+                    deletions: 0,
+                    is_binary: diff_delta.flags().is_binary(),
+                    old_size: blob_size(diff_delta.old_file()),
+                    new_size: blob_size(diff_delta.new_file()),
+                },
+            );
+
+            true
+        },
+        None,
+        None,
+        Some(&mut |_diff_delta, _hunk, line| -> bool {
+            if let Some(file) = file_changes.borrow_mut().get_mut(&*current_file.borrow()) {
+                match line.origin() {
+                    '+' => file.insertions += 1,
+                    '-' => file.deletions += 1,
+                    _ => {}
+                }
+            }
+
+            true
+        }),
+    )?;
+
+    Ok(file_changes.into_inner().into_values().collect())
+}
+
+/// Maps the commit's author, committer and `Co-authored-by:` trailers through
+/// the mailmap and sets the canonical identities used by the analyses.
+fn resolve_author(mailmap: &Mailmap, commit: &mut CommitInfo) {
+    let (author_name, author_email) = mailmap.resolve(&commit.author_name, &commit.author_email);
+    commit.author_name = author_name;
+    commit.author_email = author_email;
+    commit.author = format!("{} <{}>", commit.author_name, commit.author_email);
+
+    let (committer_name, committer_email) =
+        mailmap.resolve(&commit.committer_name, &commit.committer_email);
+    commit.committer_name = committer_name;
+    commit.committer_email = committer_email;
+    commit.committer = format!("{} <{}>", commit.committer_name, commit.committer_email);
+
+    let Ok(trailers) = git2::message_trailers_strs(&commit.message) else {
+        return;
+    };
+
+    let mut co_authors: Vec<String> = Vec::new();
+    for (_, value) in trailers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("co-authored-by"))
+    {
+        let Some((name, email)) = split_identity(value) else {
+            continue;
+        };
+        let (name, email) = mailmap.resolve(name, email);
+        let co_author = format!("{name} <{email}>");
+        if co_author != commit.author && !co_authors.contains(&co_author) {
+            co_authors.push(co_author);
+        }
+    }
+    commit.co_authors = co_authors;
+}
+
+/// Splits a `Name <email>` identity into its name and email.
+pub fn split_identity(identity: &str) -> Option<(&str, &str)> {
+    let (name, email) = identity.trim().strip_suffix('>')?.rsplit_once('<')?;
+    Some((name.trim(), email.trim()))
+}
+
+/// libgit2's `GIT_ATTR_CHECK_INCLUDE_HEAD`, which git2 does not expose.
+const ATTR_CHECK_INCLUDE_HEAD: u32 = 1 << 3;
+
+/// `.gitattributes` markers that set a file apart from hand-written source.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FileAttributes {
+    /// `linguist-generated`
+    pub generated: bool,
+    /// `linguist-vendored`
+    pub vendored: bool,
+    /// `binary` or `-diff`
+    pub binary: bool,
+}
+
+impl FileAttributes {
+    pub fn is_marked(&self) -> bool {
+        self.generated || self.vendored || self.binary
+    }
+}
+
+/// Looks up the `.gitattributes` markers of the given files as committed at
+/// HEAD. Only files with at least one marker are returned.
+pub fn read_attributes(
+    path: &Path,
+    files: impl IntoIterator<Item = String>,
+) -> Result<HashMap<String, FileAttributes>, RepoError> {
+    let repo = Repository::discover(path)?;
+    let flags = AttrCheckFlags::INDEX_ONLY
+        | AttrCheckFlags::NO_SYSTEM
+        | AttrCheckFlags::from_bits_retain(ATTR_CHECK_INCLUDE_HEAD);
+
+    let is_set = |file: &Path, name: &str| -> Result<bool, RepoError> {
+        Ok(
+            match AttrValue::from_string(repo.get_attr(file, name, flags)?) {
+                AttrValue::True => true,
+                AttrValue::String(value) => value != "false",
+                _ => false,
             },
-            None,
-            None,
-            Some(&mut |_diff_delta, _hunk, line| -> bool {
-                if let Some((_, insertions, deletions)) =
-                    file_changes.borrow_mut().get_mut(&*current_file.borrow())
-                {
-                    match line.origin() {
-                        '+' => *insertions += 1,
-                        '-' => *deletions += 1,
-                        _ => {}
+        )
+    };
+
+    let mut attributes: HashMap<String, FileAttributes> = HashMap::new();
+
+    for file in files {
+        let file_path = Path::new(&file);
+        let file_attributes = FileAttributes {
+            generated: is_set(file_path, "linguist-generated")?,
+            vendored: is_set(file_path, "linguist-vendored")?,
+            binary: is_set(file_path, "binary")?
+                || AttrValue::from_string(repo.get_attr(file_path, "diff", flags)?)
+                    == AttrValue::False,
+        };
+
+        if file_attributes.is_marked() {
+            attributes.insert(file, file_attributes);
+        }
+    }
+
+    Ok(attributes)
+}
+
+/// Root of the working tree containing `path`, if the repository has one.
+pub fn find_workdir(path: &Path) -> Result<Option<PathBuf>, RepoError> {
+    Ok(Repository::discover(path)?.workdir().map(Path::to_path_buf))
+}
+
+/// Reads the contents of the given files at HEAD. Files that no longer exist
+/// or are binary are left out.
+pub fn read_head_contents(
+    path: &Path,
+    files: impl IntoIterator<Item = String>,
+) -> Result<HashMap<String, String>, RepoError> {
+    let repo = Repository::discover(path)?;
+    let tree = repo.head()?.peel_to_tree()?;
+    let mut contents: HashMap<String, String> = HashMap::new();
+
+    for file in files {
+        let Ok(entry) = tree.get_path(Path::new(&file)) else {
+            continue;
+        };
+        let Ok(blob) = entry.to_object(&repo).and_then(|o| o.peel_to_blob()) else {
+            continue;
+        };
+
+        if !blob.is_binary() {
+            let text = String::from_utf8_lossy(blob.content()).to_string();
+            contents.insert(file, text);
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Lines of one file attributed to each author by blame, see [`read_blame`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LineOwners {
+    pub authors: HashMap<String, usize>,
+}
+
+impl LineOwners {
+    /// Lines of the file.
+    pub fn total(&self) -> usize {
+        self.authors.values().sum()
+    }
+}
+
+/// Attributes every line of the given files at `rev` (HEAD if `None`) to the
+/// author who last changed it, keyed by file then canonical author identity.
+/// Authors are resolved through the mailmap and `options.alias_file` like
+/// commit authors. Files missing at `rev`, binary or empty are left out.
+pub fn read_blame(
+    path: &Path,
+    files: impl IntoIterator<Item = String>,
+    rev: Option<&str>,
+    options: &ParseOptions,
+) -> Result<HashMap<String, LineOwners>, RepoError> {
+    let repo = Repository::discover(path)?;
+    let commit = repo
+        .revparse_single(rev.unwrap_or("HEAD"))?
+        .peel_to_commit()?;
+    let tree = commit.tree()?;
+
+    let files: Vec<String> = files
+        .into_iter()
+        .filter(|file| {
+            tree.get_path(Path::new(file))
+                .and_then(|entry| entry.to_object(&repo))
+                .and_then(|object| object.peel_to_blob())
+                .is_ok_and(|blob| !blob.is_binary())
+        })
+        .collect();
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or(0))
+        .build()?;
+
+    // Workers count lines per raw signature; identities are resolved once the
+    // results are back on this thread, which owns the mailmap.
+    let git_dir = repo.path();
+    let commit_id = commit.id();
+    let blames = pool.install(|| {
+        files
+            .par_iter()
+            .map_init(
+                || Repository::open(git_dir),
+                |worker_repo, file| {
+                    let worker_repo = worker_repo
+                        .as_ref()
+                        .map_err(|e| git2::Error::new(e.code(), e.class(), e.message()))?;
+                    let blame = worker_repo.blame_file(
+                        Path::new(file),
+                        Some(BlameOptions::new().newest_commit(commit_id)),
+                    )?;
+
+                    let mut signatures: HashMap<(String, String), usize> = HashMap::new();
+                    for hunk in blame.iter() {
+                        let signature = hunk.final_signature();
+                        let name = signature.name().unwrap_or("").to_string();
+                        let email = signature.email().unwrap_or("").to_string();
+                        *signatures.entry((name, email)).or_default() += hunk.lines_in_hunk();
                     }
+
+                    Ok((file.clone(), signatures))
+                },
+            )
+            .collect::<Result<Vec<_>, RepoError>>()
+    })?;
+
+    let mailmap = load_mailmap(&repo, options.alias_file.as_deref())?;
+    let mut owners: HashMap<String, LineOwners> = HashMap::new();
+    for (file, signatures) in blames.into_iter().filter(|p| !p.1.is_empty()) {
+        let authors = &mut owners.entry(file).or_default().authors;
+        for ((name, email), lines) in signatures {
+            let (name, email) = mailmap.resolve(&name, &email);
+            *authors.entry(format!("{name} <{email}>")).or_default() += lines;
+        }
+    }
+
+    Ok(owners)
+}
+
+/// Contents of a file at one commit, see [`read_file_history`].
+#[derive(Clone, Debug)]
+pub struct FileRevision {
+    pub hash: String,
+    pub timestamp: i64,
+    pub contents: String,
+}
+
+/// Reads the contents of `file` at every commit that touched it, newest
+/// first. Follows the file back through renames recorded in the commits.
+pub fn read_file_history(
+    path: &Path,
+    commits: &[CommitInfo],
+    file: &str,
+) -> Result<Vec<FileRevision>, RepoError> {
+    let repo = Repository::discover(path)?;
+    let mut revisions: Vec<FileRevision> = Vec::new();
+    let mut current_path = file.to_string();
+
+    for commit in commits {
+        let Some(change) = commit.file_changes.iter().find(|p| p.path == file) else {
+            continue;
+        };
+
+        let tree = repo.find_commit(Oid::from_str(&commit.hash)?)?.tree()?;
+        if let Ok(entry) = tree.get_path(Path::new(&current_path))
+            && let Ok(blob) = entry.to_object(&repo).and_then(|o| o.peel_to_blob())
+            && !blob.is_binary()
+        {
+            revisions.push(FileRevision {
+                hash: commit.hash.clone(),
+                timestamp: commit.timestamp,
+                contents: String::from_utf8_lossy(blob.content()).to_string(),
+            });
+        }
+
+        if change.status == FileStatus::Renamed
+            && let Some(old_path) = &change.old_path
+        {
+            current_path = old_path.clone();
+        }
+    }
+
+    Ok(revisions)
+}
+
+/// Rewrites every file change to the path the file has at the newest commit,
+/// so history from before a `git mv` is attributed to the file's current path.
+/// Renames are carried from each commit to its parents, so a file renamed on
+/// one branch also picks up the changes made to its old path on another.
+/// Expects every commit to come before its parents, as returned by
+/// `parse_commit_info`.
+pub fn follow_renames(commits: &mut [CommitInfo]) {
+    // Path at each pending commit to the path at the newest commit, keyed by
+    // commit hash.
+    let mut commit_paths: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    for commit in commits.iter_mut() {
+        let mut paths = commit_paths.remove(&commit.hash).unwrap_or_default();
+        let single_parent_renames = [get_renames(&commit.file_changes)];
+
+        for file in &mut commit.file_changes {
+            if let Some(current_path) = paths.get(&file.path) {
+                file.path = current_path.clone();
+            }
+        }
+
+        let renames = match commit.merge_renames.is_empty() {
+            true => &single_parent_renames[..],
+            false => &commit.merge_renames[..],
+        };
+
+        for (index, parent) in commit.parents.iter().enumerate() {
+            let mut parent_paths = match index + 1 == commit.parents.len() {
+                true => std::mem::take(&mut paths),
+                false => paths.clone(),
+            };
+            if let Some(renames) = renames.get(index) {
+                let moved: Vec<(String, String)> = renames
+                    .iter()
+                    .map(|(old_path, new_path)| {
+                        let current_path = parent_paths.get(new_path).unwrap_or(new_path);
+                        (old_path.clone(), current_path.clone())
+                    })
+                    .collect();
+                for new_path in renames.values() {
+                    parent_paths.remove(new_path);
                 }
+                parent_paths.extend(moved);
+            }
 
-                true
-            }),
-        )?;
-
-        let file_changes: Vec<_> = file_changes
-            .into_inner()
-            .into_iter()
-            .map(|(path, (status, insertions, deletions))| FileChange {
-                path,
-                status,
-                insertions,
-                deletions,
-            })
-            .collect();
-
-        let hash = hash.to_string();
-        let commit_info = commit.author();
-        let (author_name, author_email, timestamp) = (
-            commit_info.name().unwrap_or("").to_string(),
-            commit_info.email().unwrap_or("").to_string(),
-            commit_info.when().seconds(),
-        );
+            // With several children, the first one walked is the newest.
+            let pending = commit_paths.entry(parent.clone()).or_default();
+            for (path, current_path) in parent_paths {
+                pending.entry(path).or_insert(current_path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, old_path: Option<&str>) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            old_path: old_path.map(str::to_string),
+            status: match old_path {
+                Some(_) => FileStatus::Renamed,
+                None => FileStatus::Modified,
+            },
+            insertions: 1,
+            deletions: 0,
+            is_binary: false,
+            old_size: 0,
+            new_size: 2,
+        }
+    }
 
-        commits.push(CommitInfo {
-            hash,
-            author_name,
-            author_email,
-            timestamp,
-            message: commit.message().unwrap_or("").to_string(),
+    fn commit(hash: &str, parents: &[&str], file_changes: Vec<FileChange>) -> CommitInfo {
+        CommitInfo {
+            hash: hash.to_string(),
+            author: "A <a@x>".to_string(),
+            author_name: "A".to_string(),
+            author_email: "a@x".to_string(),
+            timestamp: 0,
+            committer: "A <a@x>".to_string(),
+            committer_name: "A".to_string(),
+            committer_email: "a@x".to_string(),
+            committer_timestamp: 0,
+            message: hash.to_string(),
+            co_authors: Vec::new(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
             file_changes,
-        });
+            merge_renames: Vec::new(),
+        }
     }
 
-    Ok(commits)
+    fn paths(commits: &[CommitInfo]) -> Vec<&str> {
+        commits
+            .iter()
+            .flat_map(|p| &p.file_changes)
+            .map(|p| p.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn mailmap_matches_emails_and_names_case_insensitively() {
+        let mut mailmap = Mailmap::default();
+        assert!(mailmap.add_line("Alice Smith <alice@x.com> <alice@x.com>"));
+        assert!(mailmap.add_line("Bob <bob@y.org> bobby <OLD@Y.ORG>"));
+
+        assert_eq!(
+            mailmap.resolve("Alice", "ALICE@x.com"),
+            ("Alice Smith".to_string(), "alice@x.com".to_string())
+        );
+        assert_eq!(
+            mailmap.resolve("Bobby", "old@y.org"),
+            ("Bob".to_string(), "bob@y.org".to_string())
+        );
+        assert_eq!(
+            mailmap.resolve("Robert", "Old@Y.org"),
+            ("Robert".to_string(), "old@y.org".to_string())
+        );
+    }
+
+    #[test]
+    fn load_mailmap_layers_the_alias_file() {
+        let dir = std::env::temp_dir().join(format!("gitarch-mailmap-{}", std::process::id()));
+        let repo = Repository::init(&dir).unwrap();
+        std::fs::write(dir.join(".mailmap"), "Alice <alice@x.com> <a@old.com>\n").unwrap();
+        let aliases = dir.join("aliases");
+        std::fs::write(
+            &aliases,
+            "# team aliases\n\n<alice@work.com> <A@OLD.COM>\nCarol <carol@x.com>\n",
+        )
+        .unwrap();
+        let mailmap = load_mailmap(&repo, Some(&aliases)).unwrap();
+
+        assert_eq!(
+            mailmap.resolve("a", "a@old.com"),
+            ("Alice".to_string(), "alice@work.com".to_string())
+        );
+        assert_eq!(
+            mailmap.resolve("carol", "CAROL@x.com"),
+            ("Carol".to_string(), "carol@x.com".to_string())
+        );
+
+        std::fs::write(&aliases, "Alice <alice@x.com\n").unwrap();
+        let error = load_mailmap(&repo, Some(&aliases)).unwrap_err();
+        assert!(matches!(error, RepoError::Alias(1, _)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn follow_renames_chains_renames() {
+        let mut commits = vec![
+            commit("third", &["second"], vec![change("c.txt", Some("b.txt"))]),
+            commit("second", &["first"], vec![change("b.txt", Some("a.txt"))]),
+            commit("first", &[], vec![change("a.txt", None)]),
+        ];
+
+        follow_renames(&mut commits);
+
+        assert_eq!(paths(&commits), ["c.txt", "c.txt", "c.txt"]);
+    }
+
+    #[test]
+    fn follow_renames_reaches_edits_on_other_branches() {
+        // `feature` renames the file while `main` still edits its old path;
+        // the merge is clean, so it carries the rename only relative to
+        // `main`.
+        let mut merge = commit("merge", &["main", "feature"], Vec::new());
+        merge.merge_renames = vec![
+            HashMap::from([("old.txt".to_string(), "new.txt".to_string())]),
+            HashMap::new(),
+        ];
+        let mut commits = vec![
+            merge,
+            commit("main", &["base"], vec![change("old.txt", None)]),
+            commit(
+                "feature",
+                &["base"],
+                vec![change("new.txt", Some("old.txt"))],
+            ),
+            commit("base", &[], vec![change("old.txt", None)]),
+        ];
+
+        follow_renames(&mut commits);
+
+        assert_eq!(paths(&commits), ["new.txt", "new.txt", "new.txt"]);
+    }
 }